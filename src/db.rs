@@ -1,23 +1,209 @@
-use crate::hashes;
-use crate::hashes::hash::Hash;
+use crate::hashes::algorithm::Algorithm;
 use crate::models::model::Model;
-use std::fs::File;
-use std::io::Read;
-use std::path::PathBuf;
+use crate::output::{owned_hash_map, Output, Record};
+use crate::utils::{collect_files, normalize_path, relative_to_cwd};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use rusqlite::{Connection, Error, ErrorCode};
+use rusqlite::{params, Connection, Error, ErrorCode};
 
 use crate::models::file_table::FileTable;
 use crate::models::md5_hash_table::Md5HashTable;
+use crate::models::root_table::RootTable;
 use crate::models::sha1_hash_table::Sha1HashTable;
 use crate::models::sha256_hash_table::Sha256HashTable;
-use hashes::md5::Md5Hash;
-use hashes::sha1::Sha1Hash;
-use hashes::sha256::Sha256Hash;
 use rayon::prelude::*;
 
 pub static HASH_TABLE_FILENAME: &str = "hash_table.db";
 
+// the algorithms stored in DB, and their tables
+pub static HASH_TABLES: [(Algorithm, &str); 3] = [
+    (Algorithm::Md5, "md5_hash_table"),
+    (Algorithm::Sha1, "sha1_hash_table"),
+    (Algorithm::Sha256, "sha256_hash_table"),
+];
+
+// the current time in seconds since the UNIX epoch
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// DB itself changes whenever it is updated, so it is never scanned
+pub fn is_database_file(path: &Path, db_path: &Path) -> bool {
+    if path.file_name() != db_path.file_name() {
+        return false;
+    }
+
+    match fs::canonicalize(db_path) {
+        Ok(d) => fs::canonicalize(path).is_ok_and(|p| p == d),
+        Err(_) => false,
+    }
+}
+
+// a file found under a root
+pub struct ScannedFile {
+    pub root_id: Option<i64>,
+    // the path stored in DB, relative to the root
+    pub full_path: String,
+    // the path on the filesystem
+    pub path: PathBuf,
+}
+
+// NAME=PATH, or PATH named after the directory
+pub fn parse_root(root: &str) -> Result<RootTable, String> {
+    let (name, path) = match root.split_once('=') {
+        Some((n, p)) if !n.is_empty() && !n.contains(['/', '\\']) => (Some(n), p),
+        _ => (None, root),
+    };
+
+    let path = fs::canonicalize(path).map_err(|e| format!("{}: {}", path, e))?;
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", path.display()));
+    }
+
+    let name = match name {
+        Some(n) => n.to_string(),
+        None => path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string()),
+    };
+
+    Ok(RootTable {
+        id: None,
+        name,
+        path: path.to_string_lossy().to_string(),
+    })
+}
+
+// the files under the roots
+pub fn scan_roots(roots: &[RootTable], db_path: &Path) -> Result<Vec<ScannedFile>, String> {
+    let mut scanned = vec![];
+    for root in roots.iter() {
+        let root_path = relative_to_cwd(Path::new(&root.path));
+        for path in collect_files(std::slice::from_ref(&root_path))? {
+            if is_database_file(&path, db_path) {
+                continue;
+            }
+
+            // "./*" is globbed as "a", not "./a"
+            let relative = match root_path == Path::new(".") {
+                true => path.as_path(),
+                false => path.strip_prefix(&root_path).unwrap(),
+            };
+            scanned.push(ScannedFile {
+                root_id: root.id,
+                full_path: normalize_path(relative),
+                path,
+            });
+        }
+    }
+
+    Ok(scanned)
+}
+
+// where the file in DB is on the filesystem now
+pub fn resolve_path(roots: &[RootTable], file: &FileTable) -> PathBuf {
+    match roots.iter().find(|r| r.id == file.root_id) {
+        Some(r) => relative_to_cwd(&Path::new(&r.path).join(&file.full_path)),
+        None => PathBuf::from(&file.full_path),
+    }
+}
+
+// the root and the path in DB of the file on the filesystem
+pub fn locate_path(roots: &[RootTable], path: &Path) -> Option<(Option<i64>, String)> {
+    // the file itself may be a symbolic link
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let path = fs::canonicalize(parent).ok()?.join(path.file_name()?);
+
+    roots
+        .iter()
+        .filter_map(|r| path.strip_prefix(&r.path).ok().map(|p| (r, p)))
+        .max_by_key(|(r, _)| r.path.len())
+        .map(|(r, p)| (r.id, normalize_path(p)))
+}
+
+// the roots stored in DB, or the current directory if DB has none
+pub fn stored_roots(connection: &Connection) -> Vec<RootTable> {
+    // DB created by older versions has no roots table
+    let has_roots: bool = connection
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type='table' AND name='roots'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let roots: Vec<RootTable> = match has_roots {
+        true => RootTable::all(connection)
+            .iter()
+            .map(|r| (**r).clone())
+            .collect(),
+        false => vec![],
+    };
+
+    if !roots.is_empty() {
+        return roots;
+    }
+
+    // the files stored by older versions are relative to the current directory
+    match parse_root(".") {
+        Ok(r) => vec![r],
+        Err(_) => vec![],
+    }
+}
+
+// a name cannot be bound to another path without --rebind
+pub fn store_roots(connection: &Connection, roots: &[RootTable]) -> Result<(), String> {
+    for root in roots.iter() {
+        match RootTable::find_by_name(connection, &root.name) {
+            Some(r) if r.path != root.path => {
+                return Err(format!(
+                    "root {} is already bound to {}, use --rebind",
+                    root.name, r.path
+                ));
+            }
+            Some(_) => {}
+            None => {
+                root.insert(connection);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the current directory becomes the root of the files stored by older versions
+pub fn adopt_legacy_files(connection: &Connection) {
+    let has_legacy: bool = connection
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM files WHERE root_id IS NULL",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    if !has_legacy || !RootTable::all(connection).is_empty() {
+        return;
+    }
+
+    if let Ok(root) = parse_root(".") {
+        let root_id = root.insert(connection);
+        connection
+            .execute(
+                "UPDATE files SET root_id=? WHERE root_id IS NULL",
+                [root_id],
+            )
+            .unwrap();
+    }
+}
+
 pub fn is_sqlite_error_constraint_violation(e: &Error) -> bool {
     match e.sqlite_error() {
         Some(e) => e.code == ErrorCode::ConstraintViolation,
@@ -41,12 +227,179 @@ pub fn get_id_by_file_id(
     let mut rows = stmt.query([file_id]).unwrap();
 
     let row = rows.next().unwrap().unwrap();
-    let id = row.get(0).unwrap();
+    row.get(0).unwrap()
+}
 
-    id
+pub fn find_hash_by_file_id(
+    connection: &Connection,
+    table_name: impl Into<String> + std::fmt::Display,
+    file_id: i64,
+) -> Option<String> {
+    let sql = format!(
+        r#"
+            SELECT hash FROM {} WHERE file_id=?
+        "#,
+        table_name
+    );
+
+    let mut stmt = connection.prepare(&sql).unwrap();
+    let mut rows = stmt.query([file_id]).unwrap();
+
+    rows.next().unwrap().map(|row| row.get(0).unwrap())
+}
+
+// the hashes of the file stored in each table
+pub fn find_hashes_by_file_id(connection: &Connection, file_id: i64) -> Vec<(Algorithm, String)> {
+    HASH_TABLES
+        .iter()
+        .filter_map(|(a, t)| find_hash_by_file_id(connection, *t, file_id).map(|h| (*a, h)))
+        .collect()
+}
+
+// a file in DB hashed again, to be compared with the stored hashes
+pub struct RehashedFile {
+    pub file: FileTable,
+    pub path: PathBuf,
+    pub stored: Vec<(Algorithm, String)>,
+    // the hashes of the same algorithms, or the error reading the file
+    pub actual: Result<Vec<(Algorithm, String)>, String>,
+}
+
+// each file is hashed as it is read, and only the digests are kept
+pub fn rehash_stored(
+    connection: &Connection,
+    files: Vec<(FileTable, PathBuf)>,
+) -> Vec<RehashedFile> {
+    let stored: Vec<Vec<(Algorithm, String)>> = files
+        .iter()
+        .map(|(f, _)| find_hashes_by_file_id(connection, f.id.unwrap()))
+        .collect();
+
+    files
+        .into_par_iter()
+        .zip(stored)
+        .map(|((file, path), stored)| {
+            let algorithms: Vec<Algorithm> = stored.iter().map(|(a, _)| *a).collect();
+            let actual = Algorithm::calc_all_from_path(&algorithms, &path)
+                .map(|h| algorithms.into_iter().zip(h).collect())
+                .map_err(|e| format!("{}", e));
+            RehashedFile {
+                file,
+                path,
+                stored,
+                actual,
+            }
+        })
+        .collect()
+}
+
+pub fn find_file_ids_by_hash(
+    connection: &Connection,
+    table_name: impl Into<String> + std::fmt::Display,
+    hash: &str,
+) -> Vec<i64> {
+    let sql = format!(
+        r#"
+            SELECT files.id FROM files
+            JOIN {0} ON files.id = {0}.file_id
+            WHERE {0}.hash=?
+            ORDER BY files.full_path
+        "#,
+        table_name
+    );
+
+    let mut stmt = connection.prepare(&sql).unwrap();
+    let mut rows = stmt.query([hash]).unwrap();
+    let mut ids = vec![];
+    while let Some(row) = rows.next().unwrap() {
+        ids.push(row.get(0).unwrap());
+    }
+
+    ids
+}
+
+// the files with the same SHA256, and the space wasted by all but one of them
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub files: Vec<FileTable>,
+}
+
+impl DuplicateGroup {
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+// the groups sorted by wasted space, optionally split by size in case of a hash collision
+pub fn find_duplicate_groups(connection: &Connection, same_size: bool) -> Vec<DuplicateGroup> {
+    static SQL: &str = r#"
+        SELECT sha256_hash_table.hash, files.id FROM files
+        JOIN sha256_hash_table ON files.id = sha256_hash_table.file_id
+        WHERE sha256_hash_table.hash IN (
+            SELECT hash FROM sha256_hash_table GROUP BY hash HAVING COUNT(*) > 1
+        )
+        ORDER BY sha256_hash_table.hash, files.full_path
+    "#;
+
+    let mut stmt = connection.prepare(SQL).unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut groups: Vec<DuplicateGroup> = vec![];
+    while let Some(row) = rows.next().unwrap() {
+        let hash: String = row.get(0).unwrap();
+        let file = FileTable::get(connection, row.get(1).unwrap());
+        // the records of old DB have no size
+        let size = file.size.unwrap_or_default() as u64;
+
+        match groups
+            .iter_mut()
+            .rev()
+            .take_while(|g| g.hash == hash)
+            .find(|g| !same_size || g.size == size)
+        {
+            Some(g) => {
+                g.size = g.size.max(size);
+                g.files.push(file);
+            }
+            None => groups.push(DuplicateGroup {
+                hash,
+                size,
+                files: vec![file],
+            }),
+        }
+    }
+
+    // empty files waste no space
+    groups.retain(|g| g.files.len() > 1 && g.size > 0);
+    groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then(a.hash.cmp(&b.hash)));
+    groups
+}
+
+// the hash of the file is replaced if it is already stored
+pub fn upsert_hash(
+    connection: &Connection,
+    table_name: impl Into<String> + std::fmt::Display,
+    file_id: i64,
+    hash: &str,
+) {
+    let sql = format!(
+        r#"
+            INSERT INTO {} (file_id, hash) VALUES (?, ?)
+            ON CONFLICT(file_id) DO UPDATE SET hash=excluded.hash
+        "#,
+        table_name
+    );
+
+    connection.execute(&sql, params![file_id, hash]).unwrap();
 }
 
 pub fn create_tables(conn: &mut Connection) {
+    // the files table of older versions is rebuilt, which foreign keys prevent
+    conn.execute("PRAGMA foreign_keys=false", []).unwrap();
+    // files refer to roots
+    RootTable::create(conn);
+    FileTable::create(conn);
+
     let initialize_list = [r#"
         PRAGMA foreign_keys=true
     "#];
@@ -56,7 +409,6 @@ pub fn create_tables(conn: &mut Connection) {
         tx.execute(sql, []).unwrap();
     }
 
-    FileTable::create(&tx);
     Md5HashTable::create(&tx);
     Sha1HashTable::create(&tx);
     Sha256HashTable::create(&tx);
@@ -65,90 +417,139 @@ pub fn create_tables(conn: &mut Connection) {
         Ok(()) => {}
         Err(e) => {
             eprintln!("transaction commit failed. {:?}", e);
-            return;
         }
     }
 }
 
-fn process(path: &PathBuf) -> (String, String, String) {
-    let mut file = match File::open(&path) {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("{}", e);
-            return ("".to_owned(), "".to_owned(), "".to_owned());
-        }
-    };
-
-    println!("{}", path.display());
-    let mut buffer = vec![];
-    file.read_to_end(&mut buffer).expect("Failed to read file");
+// the hashes of the algorithms stored in DB
+fn process(path: &Path) -> Result<Vec<(Algorithm, String)>, String> {
+    let algorithms: Vec<Algorithm> = HASH_TABLES.iter().map(|(a, _)| *a).collect();
+    let hashes = Algorithm::calc_all_from_path(&algorithms, path).map_err(|e| format!("{}", e))?;
 
-    let md5 = Md5Hash::calc_bytes(&buffer);
-    let sha1 = Sha1Hash::calc_bytes(&buffer);
-    let sha256 = Sha256Hash::calc_bytes(&buffer);
+    Ok(algorithms.into_iter().zip(hashes).collect())
+}
 
-    (md5, sha1, sha256)
+// DB is empty, so all files are hashed and stored
+pub fn create_database(conn: &mut Connection, file_list: &[ScannedFile], output: &mut Output) {
+    update_database(conn, file_list, true, output);
 }
 
-pub fn insert_tables(conn: &Connection, path: &PathBuf, md5: String, sha1: String, sha256: String) {
-    let f = FileTable {
-        id: None,
-        full_path: path.to_string_lossy().to_string(),
-        file_name: path.file_name().unwrap().to_string_lossy().to_string(),
-    };
+// only the new files and the files whose metadata changed are hashed, unless paranoid
+pub fn update_database(
+    conn: &mut Connection,
+    file_list: &[ScannedFile],
+    paranoid: bool,
+    output: &mut Output,
+) {
+    create_tables(conn);
 
-    let file_id = f.insert(conn);
+    // the files to hash, with the id of the stored record
+    let targets: Vec<(&PathBuf, FileTable, Option<i64>)> = file_list
+        .iter()
+        .filter_map(|f| {
+            let file = FileTable {
+                full_path: f.full_path.clone(),
+                root_id: f.root_id,
+                ..FileTable::from_path(&f.path)
+            };
+            match FileTable::find_by_path(conn, f.root_id, &f.full_path) {
+                Some(stored) if !paranoid && stored.same_metadata(&file) => None,
+                stored => Some((&f.path, file, stored.and_then(|s| s.id))),
+            }
+        })
+        .collect();
 
-    Md5HashTable {
-        id: None,
-        file_id,
-        hash: md5,
-    }
-    .insert(conn);
+    let hashes: Vec<Result<Vec<(Algorithm, String)>, String>> =
+        targets.par_iter().map(|(f, _, _)| process(f)).collect();
 
-    Sha1HashTable {
-        id: None,
-        file_id,
-        hash: sha1,
+    let tx = conn.transaction().unwrap();
+    for ((f, file, id), hashes) in targets.into_iter().zip(hashes) {
+        // the file that could not be read is left as it is in DB
+        let hashes = match hashes {
+            Ok(h) => h,
+            Err(e) => {
+                output.emit(Record::error(f.to_string_lossy(), &e), |_| {
+                    eprintln!("{}: {}", f.display(), e)
+                });
+                continue;
+            }
+        };
+
+        // the file is verified when it is hashed
+        let file = FileTable {
+            id,
+            verified: Some(unix_time()),
+            ..file
+        };
+        let (file_id, status) = match id {
+            Some(_) => (file.update(&tx), "UPDATED"),
+            None => (file.insert(&tx), "STORED"),
+        };
+        for ((_, table_name), (_, hash)) in HASH_TABLES.iter().zip(hashes.iter()) {
+            upsert_hash(&tx, *table_name, file_id, hash);
+        }
+
+        let record = Record {
+            hashes: owned_hash_map(&hashes),
+            status: Some(status.to_string()),
+            ..Record::for_file(f)
+        };
+        output.emit(record, |_| println!("{}", f.display()));
     }
-    .insert(conn);
 
-    Sha256HashTable {
-        id: None,
-        file_id,
-        hash: sha256,
+    match tx.commit() {
+        Ok(()) => {}
+        Err(e) => {
+            eprintln!("transaction commit failed. {:?}", e);
+        }
     }
-    .insert(conn);
 }
 
-pub fn create_database(conn: &mut Connection, file_list: &Vec<PathBuf>) {
-    let hashes: Vec<(&PathBuf, String, String, String)> = file_list
-        .par_iter()
-        .map(|f| {
-            let (md5, sha1, sha256) = process(&f);
-            return (f, md5, sha1, sha256);
-        })
+// the files in DB that are not in the file list are deleted, with their hashes
+pub fn prune_database(
+    conn: &mut Connection,
+    file_list: &[ScannedFile],
+    output: &mut Output,
+) -> usize {
+    let existing: HashSet<(Option<i64>, &String)> = file_list
+        .iter()
+        .map(|f| (f.root_id, &f.full_path))
         .collect();
+    let roots = stored_roots(conn);
 
-    create_tables(conn);
+    // an empty root may be an unmounted mount point, so its files are kept
+    let scanned: HashSet<Option<i64>> = file_list.iter().map(|f| f.root_id).collect();
+    for root in roots.iter().filter(|r| !scanned.contains(&r.id)) {
+        eprintln!("{}: no files found, not pruned", root.path);
+    }
 
     let tx = conn.transaction().unwrap();
-    hashes.iter().for_each(|e| {
-        let (f, md5, sha1, sha256) = e;
-        insert_tables(
-            &tx,
-            &f,
-            md5.to_string(),
-            sha1.to_string(),
-            sha256.to_string(),
-        );
-    });
+    let mut pruned = 0;
+    for file in FileTable::all(&tx).iter() {
+        if !scanned.contains(&file.root_id) || existing.contains(&(file.root_id, &file.full_path)) {
+            continue;
+        }
+
+        for (_, table_name) in HASH_TABLES.iter() {
+            let sql = format!("DELETE FROM {} WHERE file_id=?", table_name);
+            tx.execute(&sql, [file.id.unwrap()]).unwrap();
+        }
+        file.delete(&tx);
+        pruned += 1;
+
+        let path = resolve_path(&roots, file);
+        let record = Record {
+            status: Some("PRUNED".to_string()),
+            ..Record::new(path.to_string_lossy())
+        };
+        output.emit(record, |_| println!("{}: PRUNED", path.display()));
+    }
 
     match tx.commit() {
-        Ok(()) => {}
+        Ok(()) => pruned,
         Err(e) => {
             eprintln!("transaction commit failed. {:?}", e);
-            return;
+            0
         }
     }
 }
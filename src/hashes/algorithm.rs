@@ -0,0 +1,152 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use clap::ValueEnum;
+
+use super::{
+    blake3::Blake3Hash,
+    crc32::Crc32Hash,
+    hash::{Hash, HashState, CHUNK_SIZE},
+    md5::Md5Hash,
+    sha1::Sha1Hash,
+    sha256::Sha256Hash,
+    sha3_256::Sha3_256Hash,
+};
+
+// ordered from the weakest to the strongest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
+pub enum Algorithm {
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::Crc32,
+        Algorithm::Md5,
+        Algorithm::Sha1,
+        Algorithm::Sha256,
+        Algorithm::Sha3_256,
+        Algorithm::Blake3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Crc32 => "crc32",
+            Algorithm::Md5 => "md5",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha3_256 => "sha3-256",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    // e.g., the extension of b3sum files
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Algorithm::Sha3_256 => &["sha3_256"],
+            Algorithm::Blake3 => &["b3"],
+            _ => &[],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| {
+            a.name().eq_ignore_ascii_case(name)
+                || a.aliases().iter().any(|n| n.eq_ignore_ascii_case(name))
+        })
+    }
+
+    // the algorithm that has the hash length, or an error if none or ambiguous
+    pub fn from_hash_length(length: usize, hint: Option<Algorithm>) -> Result<Self, String> {
+        let candidates: Vec<Algorithm> = Self::ALL
+            .into_iter()
+            .filter(|a| a.get_hash_length() == length)
+            .collect();
+
+        match (candidates.as_slice(), hint) {
+            ([], _) => Err(format!("invalid hash length: {}", length)),
+            (_, Some(h)) if candidates.contains(&h) => Ok(h),
+            (_, Some(h)) => Err(format!("invalid {} hash length: {}", h.name(), length)),
+            ([a], None) => Ok(*a),
+            (_, None) => Err(format!(
+                "ambiguous hash length: {} ({}), specify --algo",
+                length,
+                candidates
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    pub fn get_hash_length(&self) -> usize {
+        match self {
+            Algorithm::Crc32 => Crc32Hash::get_hash_length(),
+            Algorithm::Md5 => Md5Hash::get_hash_length(),
+            Algorithm::Sha1 => Sha1Hash::get_hash_length(),
+            Algorithm::Sha256 => Sha256Hash::get_hash_length(),
+            Algorithm::Sha3_256 => Sha3_256Hash::get_hash_length(),
+            Algorithm::Blake3 => Blake3Hash::get_hash_length(),
+        }
+    }
+
+    pub fn calc_bytes(&self, bytes: &[u8]) -> String {
+        match self {
+            Algorithm::Crc32 => Crc32Hash::calc_bytes(bytes),
+            Algorithm::Md5 => Md5Hash::calc_bytes(bytes),
+            Algorithm::Sha1 => Sha1Hash::calc_bytes(bytes),
+            Algorithm::Sha256 => Sha256Hash::calc_bytes(bytes),
+            Algorithm::Sha3_256 => Sha3_256Hash::calc_bytes(bytes),
+            Algorithm::Blake3 => Blake3Hash::calc_bytes(bytes),
+        }
+    }
+
+    pub fn state(&self) -> Box<dyn HashState> {
+        match self {
+            Algorithm::Crc32 => Crc32Hash::state(),
+            Algorithm::Md5 => Md5Hash::state(),
+            Algorithm::Sha1 => Sha1Hash::state(),
+            Algorithm::Sha256 => Sha256Hash::state(),
+            Algorithm::Sha3_256 => Sha3_256Hash::state(),
+            Algorithm::Blake3 => Blake3Hash::state(),
+        }
+    }
+
+    // the hashes of the algorithms in a single pass, with the file never held in memory
+    pub fn calc_all_from_path(algorithms: &[Algorithm], path: &Path) -> io::Result<Vec<String>> {
+        let mut file = File::open(path)?;
+        let mut states: Vec<Box<dyn HashState>> = algorithms.iter().map(|a| a.state()).collect();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => states.iter_mut().for_each(|s| s.update(&buf[..n])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(states.into_iter().map(|s| s.finish()).collect())
+    }
+
+    pub fn calc_from_path(&self, path: &Path) -> String {
+        match self {
+            Algorithm::Crc32 => Crc32Hash::calc_from_path(path),
+            Algorithm::Md5 => Md5Hash::calc_from_path(path),
+            Algorithm::Sha1 => Sha1Hash::calc_from_path(path),
+            Algorithm::Sha256 => Sha256Hash::calc_from_path(path),
+            Algorithm::Sha3_256 => Sha3_256Hash::calc_from_path(path),
+            Algorithm::Blake3 => Blake3Hash::calc_from_path(path),
+        }
+    }
+}
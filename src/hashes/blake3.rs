@@ -0,0 +1,27 @@
+use crate::hashes::hash::{Hash, HashState};
+
+pub struct Blake3Hash {}
+
+impl Hash for Blake3Hash {
+    fn calc_bytes(bytes: &[u8]) -> String {
+        blake3::hash(bytes).to_hex().to_string()
+    }
+
+    fn state() -> Box<dyn HashState> {
+        Box::new(blake3::Hasher::new())
+    }
+
+    fn get_hash_length() -> usize {
+        64
+    }
+}
+
+impl HashState for blake3::Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        blake3::Hasher::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        self.finalize().to_hex().to_string()
+    }
+}
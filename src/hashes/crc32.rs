@@ -0,0 +1,31 @@
+use crc32fast::Hasher;
+
+use crate::hashes::hash::{Hash, HashState};
+
+pub struct Crc32Hash {}
+
+impl Hash for Crc32Hash {
+    fn calc_bytes(bytes: &[u8]) -> String {
+        let mut crc32 = Hasher::new();
+        crc32.update(bytes);
+        format!("{:08x}", crc32.finalize())
+    }
+
+    fn state() -> Box<dyn HashState> {
+        Box::new(Hasher::new())
+    }
+
+    fn get_hash_length() -> usize {
+        8
+    }
+}
+
+impl HashState for Hasher {
+    fn update(&mut self, bytes: &[u8]) {
+        Hasher::update(self, bytes);
+    }
+
+    fn finish(self: Box<Self>) -> String {
+        format!("{:08x}", self.finalize())
+    }
+}
@@ -1,28 +1,50 @@
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+};
 
-use super::{md5::Md5Hash, sha1::Sha1Hash, sha256::Sha256Hash};
+use base64::prelude::*;
 
-pub trait Hash {
-    fn calc(value: impl Into<String>) -> String {
-        Self::calc_bytes(value.into().as_bytes())
-    }
-    async fn acalc(value: impl Into<String>) -> String {
-        Self::calc(value)
-    }
+use crate::openpgp::{ClearSigned, Keyring, Signer};
+use crate::output::{hash_map, Output, Record};
+use crate::signature::{PublicKey, SignatureFormat};
+use crate::utils::{device_of, is_rotational};
+
+use super::{algorithm::Algorithm, manifest::Manifest};
 
+// the state of a hash calculated chunk by chunk
+pub trait HashState: Send {
+    fn update(&mut self, bytes: &[u8]);
+    fn finish(self: Box<Self>) -> String;
+}
+
+pub const CHUNK_SIZE: usize = 1 << 16;
+
+pub trait Hash {
     fn calc_bytes(bytes: &[u8]) -> String;
-    async fn acalc_bytes(bytes: &[u8]) -> String {
-        Self::calc_bytes(bytes)
-    }
 
-    fn calc_from_file(file: &mut File) -> String {
-        let mut buf = String::new();
-        let _ = file.read_to_string(&mut buf);
-        Self::calc(buf)
-    }
+    fn state() -> Box<dyn HashState>;
 
-    async fn acalc_from_file(file: &mut File) -> String {
-        Self::calc_from_file(file)
+    // the file is read in chunks, so it need not fit in memory
+    fn calc_from_file(file: &mut File) -> String {
+        let mut state = Self::state();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => state.update(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        state.finish()
     }
 
     fn calc_from_path(path: &Path) -> String {
@@ -30,92 +52,406 @@ pub trait Hash {
         Self::calc_from_file(&mut file)
     }
 
-    async fn acalc_from_path(path: &Path) -> String {
-        Self::calc_from_path(path)
-    }
-
     fn get_hash_length() -> usize;
 }
 
+pub const STDIN_FILENAME: &str = "-";
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CheckSummary {
+    pub ok: usize,
+    pub failed: usize,
+}
+
+impl CheckSummary {
+    pub fn merge(&mut self, other: &CheckSummary) {
+        self.ok += other.ok;
+        self.failed += other.failed;
+    }
+}
+
 pub struct ChecksumFileUtils;
 
 impl ChecksumFileUtils {
     const DELIMITER: &'static str = "  ";
+    const OK: &'static str = "OK";
+    const FAILED: &'static str = "FAILED";
 
-    pub fn check(checksum_filepath: &Path, ignore_missing: bool) -> Result<(), String> {
-        let hash_filename_map = match Self::parse_checksum_file(checksum_filepath) {
-            Ok(m) => m,
+    pub fn check(
+        manifest: &Manifest,
+        ignore_missing: bool,
+        jobs: Option<usize>,
+        output: &mut Output,
+    ) -> Result<CheckSummary, String> {
+        // (path, algorithm, hash) in manifest order
+        let mut targets: Vec<(&Path, Algorithm, &String)> = vec![];
+        let mut invalid_entry = None;
+        for entry in manifest.entries.iter() {
+            let path = Path::new(&entry.path);
+            if !path.exists() && ignore_missing {
+                continue;
+            }
+
+            match entry.strongest_hash() {
+                Some((algorithm, hash)) => targets.push((path, algorithm, hash)),
+                None => {
+                    invalid_entry = Some(format!("no supported hash: {}", entry.path));
+                    break;
+                }
+            }
+        }
+
+        let pool = match rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.unwrap_or(0))
+            .build()
+        {
+            Ok(p) => p,
             Err(e) => {
                 return Err(format!("{}", e));
             }
         };
 
-        for (hash, filename) in hash_filename_map {
-            let path = Path::new(&filename);
-            if !path.exists() && ignore_missing {
-                continue;
-            }
+        // the files on a spinning disk are read one by one, the others in parallel
+        let mut devices: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        for (i, (path, _, _)) in targets.iter().enumerate() {
+            devices.entry(device_of(path)).or_default().push(i);
+        }
 
-            match Self::check_hash(&hash, &path) {
-                Ok(r) => {
-                    println!("{}: {}", filename, r);
-                }
-                Err(e) => {
-                    return Err(format!("{}", e));
+        let aborted = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel();
+        let mut summary = CheckSummary::default();
+        let result = thread::scope(|s| {
+            s.spawn(|| {
+                pool.scope(|ps| {
+                    for (device, indices) in devices.iter() {
+                        let check = |i: usize, tx: &mpsc::Sender<_>| {
+                            if aborted.load(Ordering::Relaxed) {
+                                return;
+                            }
+                            let (path, algorithm, hash) = targets[i];
+                            let _ = tx.send((i, Self::check_hash(algorithm, hash, path)));
+                        };
+
+                        if is_rotational(*device) {
+                            let tx = tx.clone();
+                            ps.spawn(move |_| indices.iter().for_each(|i| check(*i, &tx)));
+                        } else {
+                            for i in indices.iter() {
+                                let tx = tx.clone();
+                                ps.spawn(move |_| check(*i, &tx));
+                            }
+                        }
+                    }
+                });
+                drop(tx);
+            });
+
+            // print the results in manifest order as soon as they are available
+            let mut pending: BTreeMap<usize, Result<&'static str, String>> = BTreeMap::new();
+            let mut next = 0;
+            for (i, r) in rx.iter() {
+                pending.insert(i, r);
+                while let Some(r) = pending.remove(&next) {
+                    let (path, algorithm, hash) = targets[next];
+                    next += 1;
+                    match r {
+                        Ok(r) => {
+                            output.emit(Self::check_record(path, algorithm, hash, r), |_| {
+                                println!("{}: {}", path.display(), r)
+                            });
+                            if r == Self::OK {
+                                summary.ok += 1;
+                            } else {
+                                summary.failed += 1;
+                            }
+                        }
+                        Err(e) => {
+                            aborted.store(true, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                    }
                 }
-            };
-        }
+            }
+
+            Ok(())
+        });
 
-        Ok(())
+        result?;
+        match invalid_entry {
+            Some(e) => Err(e),
+            None => Ok(summary),
+        }
     }
 
-    fn parse_checksum_file(checksum_file: &Path) -> Result<Vec<(String, String)>, String> {
+    fn read_checksum_file(checksum_file: &Path) -> Result<String, String> {
+        let mut buf = String::new();
+        if checksum_file.as_os_str() == STDIN_FILENAME {
+            return match io::stdin().lock().read_to_string(&mut buf) {
+                Ok(_) => Ok(buf),
+                Err(e) => Err(format!("{}", e)),
+            };
+        }
+
         let mut checksum_file = match File::open(checksum_file) {
             Ok(f) => f,
             Err(e) => {
                 return Err(format!("{}", e));
             }
         };
-        let mut buf = String::new();
         let _ = checksum_file.read_to_string(&mut buf);
 
-        let delimiter_length = Self::DELIMITER.len();
+        Ok(buf)
+    }
+
+    pub fn read_manifest(
+        checksum_file: &Path,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Manifest, String> {
+        let buf = Self::read_checksum_file(checksum_file)?;
+        Manifest::parse(checksum_file, &buf, algorithm)
+    }
+
+    // the manifest is parsed only if FILE.minisig or FILE.sig is valid
+    pub fn read_signed_manifest(
+        checksum_file: &Path,
+        algorithm: Option<Algorithm>,
+        public_key: &PublicKey,
+    ) -> Result<Manifest, String> {
+        if checksum_file.as_os_str() == STDIN_FILENAME {
+            return Err("the signature of stdin cannot be verified".to_string());
+        }
+
+        let signature_path = match Self::signature_path(checksum_file) {
+            Some(p) => p,
+            None => {
+                return Err(format!(
+                    "signature not found: {}",
+                    SignatureFormat::Minisign
+                        .signature_path(checksum_file)
+                        .display()
+                ));
+            }
+        };
+
+        let content = fs::read(checksum_file).map_err(|e| format!("{}", e))?;
+        let signature = fs::read_to_string(&signature_path)
+            .map_err(|e| format!("{}: {}", signature_path.display(), e))?;
+        public_key
+            .verify(&content, &signature)
+            .map_err(|e| format!("{}: {}", signature_path.display(), e))?;
+
+        match String::from_utf8(content) {
+            Ok(buf) => Manifest::parse(checksum_file, &buf, algorithm),
+            Err(e) => Err(format!("{}", e)),
+        }
+    }
 
-        // key ... checksum
-        // value ... filename
-        let mut checksum_filename_vec = Vec::<(String, String)>::new();
-        for line in buf.lines() {
-            let space_pos = match line.find(Self::DELIMITER) {
-                Some(n) => n,
+    // the detached signature used to verify the checksum file, FILE.minisig or FILE.sig
+    pub fn signature_path(checksum_file: &Path) -> Option<PathBuf> {
+        SignatureFormat::ALL
+            .iter()
+            .map(|f| f.signature_path(checksum_file))
+            .find(|p| p.exists())
+    }
+
+    // the manifest is parsed only if it is clear-signed by a key in the keyring
+    pub fn read_clear_signed_manifest(
+        checksum_file: &Path,
+        algorithm: Option<Algorithm>,
+        keyring: &Keyring,
+    ) -> Result<(Manifest, Signer), String> {
+        let buf = Self::read_checksum_file(checksum_file)?;
+        if !ClearSigned::is_clear_signed(&buf) {
+            return Err("not an OpenPGP clear-signed file".to_string());
+        }
+
+        let clear_signed = ClearSigned::parse(&buf)?;
+        let signer = clear_signed.verify(keyring)?;
+        let manifest = Manifest::parse(checksum_file, &clear_signed.text, algorithm)?;
+
+        Ok((manifest, signer))
+    }
+
+    pub fn sidecar_algorithm(sidecar: &Path) -> Option<Algorithm> {
+        sidecar
+            .extension()
+            .and_then(|e| Algorithm::from_name(&e.to_string_lossy()))
+    }
+
+    pub fn sidecar_path(path: &Path, algorithm: Algorithm) -> PathBuf {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".");
+        sidecar.push(algorithm.name());
+        PathBuf::from(sidecar)
+    }
+
+    // foo.iso.sha256 -> foo.iso
+    pub fn sidecar_target(sidecar: &Path) -> Option<PathBuf> {
+        Self::sidecar_algorithm(sidecar)?;
+        let target = sidecar.with_extension("");
+        if target.file_name().is_some() {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    pub fn check_sidecar(sidecar: &Path, output: &mut Output) -> Result<CheckSummary, String> {
+        let algorithm = match Self::sidecar_algorithm(sidecar) {
+            Some(a) => a,
+            None => {
+                return Err(format!("{} is not a sidecar file", sidecar.display()));
+            }
+        };
+
+        let buf = Self::read_checksum_file(sidecar)?;
+        let (hash, filename) = Self::parse_sidecar(&buf)?;
+        if hash.len() != algorithm.get_hash_length() {
+            return Err(format!(
+                "invalid hash length: {}  {}",
+                hash,
+                sidecar.display()
+            ));
+        }
+
+        // the filename is relative to the sidecar, and may be omitted
+        let target = match filename {
+            Some(f) => sidecar.with_file_name(f),
+            None => match Self::sidecar_target(sidecar) {
+                Some(t) => t,
                 None => {
-                    return Err(format!("delimiter not found"));
+                    return Err(format!("{} is not a sidecar file", sidecar.display()));
                 }
-            };
+            },
+        };
 
-            let hash = line[0..space_pos].to_owned();
-            let filename = line[space_pos + delimiter_length..line.len()].to_owned();
+        let matched = Self::check_hash(algorithm, &hash, &target)?;
+        output.emit(
+            Self::check_record(&target, algorithm, &hash, matched),
+            |_| println!("{}: {} ({})", target.display(), matched, algorithm.name()),
+        );
 
-            checksum_filename_vec.push((hash, filename));
+        let mut summary = CheckSummary::default();
+        if matched == Self::OK {
+            summary.ok += 1;
+        } else {
+            summary.failed += 1;
         }
 
-        Ok(checksum_filename_vec)
+        Ok(summary)
     }
 
-    fn check_hash(hash: &String, path: &Path) -> Result<&'static str, String> {
-        if !path.exists() {
-            return Err(format!("{} not found", path.display()));
+    fn parse_sidecar(content: &str) -> Result<(String, Option<String>), String> {
+        // checksum
+        // checksum  filename
+        // checksum *filename
+        let line = match content.lines().map(|l| l.trim()).find(|l| !l.is_empty()) {
+            Some(l) => l,
+            None => {
+                return Err("checksum not found".to_string());
+            }
+        };
+
+        match line.split_once(char::is_whitespace) {
+            Some((hash, filename)) => {
+                let filename = filename.trim_start();
+                let filename = filename.strip_prefix('*').unwrap_or(filename);
+                Ok((hash.to_lowercase(), Some(filename.to_owned())))
+            }
+            None => Ok((line.to_lowercase(), None)),
         }
+    }
 
-        let matched = if hash.len() == Md5Hash::get_hash_length() {
-            hash == &Md5Hash::calc_from_path(&path)
-        } else if hash.len() == Sha1Hash::get_hash_length() {
-            hash == &Sha1Hash::calc_from_path(&path)
-        } else if hash.len() == Sha256Hash::get_hash_length() {
-            hash == &Sha256Hash::calc_from_path(&path)
+    pub fn format_sidecar(hash: &str, filename: &str) -> String {
+        format!("{}{}{}", hash, Self::DELIMITER, filename)
+    }
+
+    // hex or base64, optionally prefixed with "ALGORITHM:" or "ALGORITHM-" (SRI)
+    pub fn parse_digest(
+        digest: &str,
+        algorithm: Option<Algorithm>,
+    ) -> Result<(Algorithm, String), String> {
+        let prefixed = digest
+            .split_once(':')
+            .or_else(|| digest.rsplit_once('-'))
+            .and_then(|(name, value)| Algorithm::from_name(name).map(|a| (Some(a), value)));
+        let (algorithm, value) = prefixed.unwrap_or((algorithm, digest));
+
+        let is_hex = value.chars().all(|c| c.is_ascii_hexdigit())
+            && Algorithm::ALL
+                .into_iter()
+                .any(|a| a.get_hash_length() == value.len());
+        let hash = if is_hex {
+            value.to_lowercase()
+        } else {
+            match BASE64_STANDARD.decode(value) {
+                Ok(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                Err(_) => {
+                    return Err(format!("invalid digest: {}", digest));
+                }
+            }
+        };
+
+        let algorithm = match algorithm {
+            Some(a) if a.get_hash_length() == hash.len() => a,
+            Some(a) => {
+                return Err(format!("invalid {} digest: {}", a.name(), digest));
+            }
+            None => Algorithm::from_hash_length(hash.len(), None)?,
+        };
+
+        Ok((algorithm, hash))
+    }
+
+    pub fn check_digest(
+        path: &Path,
+        algorithm: Algorithm,
+        hash: &String,
+        output: &mut Output,
+    ) -> Result<CheckSummary, String> {
+        let matched = if path.as_os_str() == STDIN_FILENAME {
+            let mut buf = vec![];
+            if let Err(e) = io::stdin().lock().read_to_end(&mut buf) {
+                return Err(format!("{}", e));
+            }
+            if *hash == algorithm.calc_bytes(&buf) {
+                Self::OK
+            } else {
+                Self::FAILED
+            }
         } else {
-            return Err(format!("invalid hash length: {}  {}", hash, path.display()));
+            Self::check_hash(algorithm, hash, path)?
         };
 
-        Ok(if matched { "OK" } else { "FAILED" })
+        output.emit(Self::check_record(path, algorithm, hash, matched), |_| {
+            println!("{}: {}", path.display(), matched)
+        });
+
+        let mut summary = CheckSummary::default();
+        if matched == Self::OK {
+            summary.ok += 1;
+        } else {
+            summary.failed += 1;
+        }
+
+        Ok(summary)
+    }
+
+    fn check_hash(algorithm: Algorithm, hash: &str, path: &Path) -> Result<&'static str, String> {
+        if !path.exists() {
+            return Err(format!("{} not found", path.display()));
+        }
+
+        let matched = hash.to_lowercase() == algorithm.calc_from_path(path);
+
+        Ok(if matched { Self::OK } else { Self::FAILED })
+    }
+
+    fn check_record(path: &Path, algorithm: Algorithm, hash: &String, result: &str) -> Record {
+        Record {
+            hashes: hash_map([(algorithm, Some(hash))]),
+            status: Some(result.to_string()),
+            ..Record::for_file(path)
+        }
     }
 }
@@ -0,0 +1,162 @@
+use std::{fs, path::Path};
+
+use super::algorithm::Algorithm;
+
+#[derive(Debug, Clone, Default)]
+pub struct HashdeepEntry {
+    pub size: Option<u64>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+    pub filename: String,
+}
+
+impl HashdeepEntry {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let algorithms = [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256];
+        let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let hashes = Algorithm::calc_all_from_path(&algorithms, path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let [md5, sha1, sha256] = hashes.try_into().unwrap();
+
+        Ok(Self {
+            size: Some(metadata.len()),
+            md5: Some(md5),
+            sha1: Some(sha1),
+            sha256: Some(sha256),
+            filename: path.to_string_lossy().to_string(),
+        })
+    }
+
+    // the strongest digest in the entry, used as the key to find moved files
+    pub fn strongest_hash(&self) -> Option<&String> {
+        self.sha256
+            .as_ref()
+            .or(self.sha1.as_ref())
+            .or(self.md5.as_ref())
+    }
+
+    // every size and digest known by `self` must be equal to the ones of `other`
+    pub fn matches(&self, other: &HashdeepEntry) -> bool {
+        fn eq<T: PartialEq>(known: &Option<T>, actual: &Option<T>) -> bool {
+            match (known, actual) {
+                (Some(k), Some(a)) => k == a,
+                (Some(_), None) => false,
+                (None, _) => true,
+            }
+        }
+
+        self.strongest_hash().is_some()
+            && eq(&self.size, &other.size)
+            && eq(&self.md5, &other.md5)
+            && eq(&self.sha1, &other.sha1)
+            && eq(&self.sha256, &other.sha256)
+    }
+}
+
+pub struct HashdeepUtils;
+
+impl HashdeepUtils {
+    const HEADER: &'static str = "%%%% HASHDEEP-1.0";
+    const HEADER_PREFIX: &'static str = "%%%%";
+    const COMMENT_PREFIX: &'static str = "##";
+    const DELIMITER: char = ',';
+
+    pub fn is_hashdeep(content: &str) -> bool {
+        content.starts_with(Self::HEADER)
+    }
+
+    pub fn parse(content: &str) -> Result<Vec<HashdeepEntry>, String> {
+        let mut lines = content.lines();
+        match lines.next() {
+            Some(l) if l.trim_end() == Self::HEADER => {}
+            _ => {
+                return Err("hashdeep header not found".to_string());
+            }
+        };
+
+        // %%%% size,md5,sha256,filename
+        let columns: Vec<String> = match lines.next() {
+            Some(l) if l.starts_with(Self::HEADER_PREFIX) => l[Self::HEADER_PREFIX.len()..]
+                .trim()
+                .split(Self::DELIMITER)
+                .map(|c| c.trim().to_lowercase())
+                .collect(),
+            _ => {
+                return Err("hashdeep column header not found".to_string());
+            }
+        };
+
+        if columns.last().map(|c| c.as_str()) != Some("filename") {
+            return Err("hashdeep column header must end with filename".to_string());
+        }
+
+        let mut entries = vec![];
+        for line in lines {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with(Self::COMMENT_PREFIX) {
+                continue;
+            }
+
+            // the filename may contain the delimiter
+            let fields: Vec<&str> = line.splitn(columns.len(), Self::DELIMITER).collect();
+            if fields.len() != columns.len() {
+                return Err(format!("invalid hashdeep line: {}", line));
+            }
+
+            let mut entry = HashdeepEntry::default();
+            for (column, field) in columns.iter().zip(fields) {
+                match column.as_str() {
+                    "size" => {
+                        entry.size = match field.parse() {
+                            Ok(n) => Some(n),
+                            Err(_) => {
+                                return Err(format!("invalid size: {}", line));
+                            }
+                        }
+                    }
+                    "md5" => entry.md5 = Some(field.to_lowercase()),
+                    "sha1" => entry.sha1 = Some(field.to_lowercase()),
+                    "sha256" => entry.sha256 = Some(field.to_lowercase()),
+                    "filename" => entry.filename = field.to_owned(),
+                    // tiger, whirlpool, ... are not supported
+                    _ => {}
+                }
+            }
+
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    pub fn format_header(invoked_from: &Path, command_line: &str) -> String {
+        [
+            Self::HEADER.to_string(),
+            format!("{} size,md5,sha1,sha256,filename", Self::HEADER_PREFIX),
+            format!(
+                "{} Invoked from: {}",
+                Self::COMMENT_PREFIX,
+                invoked_from.display()
+            ),
+            format!("{} $ {}", Self::COMMENT_PREFIX, command_line),
+            Self::COMMENT_PREFIX.to_string(),
+        ]
+        .join("\n")
+    }
+
+    pub fn format_line(entry: &HashdeepEntry) -> String {
+        fn or_empty<T: ToString>(v: &Option<T>) -> String {
+            v.as_ref().map(|v| v.to_string()).unwrap_or_default()
+        }
+
+        format!(
+            "{},{},{},{},{}",
+            or_empty(&entry.size),
+            or_empty(&entry.md5),
+            or_empty(&entry.sha1),
+            or_empty(&entry.sha256),
+            entry.filename
+        )
+    }
+}
@@ -0,0 +1,542 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    path::Path,
+};
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::openpgp::ClearSigned;
+use crate::output::Record;
+
+use super::{
+    algorithm::Algorithm,
+    hashdeep::{HashdeepEntry, HashdeepUtils},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ManifestFormat {
+    // hash  filename
+    Gnu,
+    // ALGORITHM (filename) = hash
+    Bsd,
+    // filename CRC32
+    Sfv,
+    // %%%% HASHDEEP-1.0
+    Hashdeep,
+    // --output json
+    Json,
+    // --output csv
+    Csv,
+    // --output tsv
+    Tsv,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: Option<u64>,
+    pub hashes: BTreeMap<Algorithm, String>,
+}
+
+impl ManifestEntry {
+    pub fn strongest_hash(&self) -> Option<(Algorithm, &String)> {
+        self.hashes.iter().next_back().map(|(a, h)| (*a, h))
+    }
+
+    // the same content if the hashes of the common algorithms and the sizes are equal
+    pub fn matches(&self, other: &ManifestEntry) -> bool {
+        let mut common = self
+            .hashes
+            .iter()
+            .filter_map(|(a, h)| other.hashes.get(a).map(|o| (h, o)))
+            .peekable();
+        let sizes_match = match (self.size, other.size) {
+            (Some(s), Some(o)) => s == o,
+            _ => true,
+        };
+
+        common.peek().is_some() && sizes_match && common.all(|(h, o)| h == o)
+    }
+
+    pub fn to_record(&self) -> Record {
+        Record {
+            size: self.size,
+            hashes: self
+                .hashes
+                .iter()
+                .map(|(a, h)| (a.name().to_string(), h.clone()))
+                .collect(),
+            ..Record::new(&self.path)
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    const DELIMITER: &'static str = "  ";
+    const BINARY_DELIMITER: &'static str = " *";
+    const SFV_EXTENSION: &'static str = "sfv";
+    const SFV_COMMENT: &'static str = ";";
+    const SUMS_SUFFIX: &'static str = "SUMS";
+
+    // the ALGORITHM is used for the hashes of the same length, if given or detected from the path
+    pub fn parse(
+        manifest_path: &Path,
+        content: &str,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Self, String> {
+        if ClearSigned::is_clear_signed(content) {
+            let clear_signed = ClearSigned::parse(content)?;
+            return Self::parse(manifest_path, &clear_signed.text, algorithm);
+        }
+
+        let algorithm = algorithm.or_else(|| Self::detect_algorithm(manifest_path));
+        Self::parse_as(
+            Self::detect_format(manifest_path, content),
+            content,
+            algorithm,
+        )
+    }
+
+    pub fn parse_as(
+        format: ManifestFormat,
+        content: &str,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Self, String> {
+        let entries = match format {
+            ManifestFormat::Gnu => Self::parse_gnu(content, algorithm)?,
+            ManifestFormat::Bsd => Self::parse_bsd(content)?,
+            ManifestFormat::Sfv => Self::parse_sfv(content)?,
+            ManifestFormat::Hashdeep => Self::parse_hashdeep(content)?,
+            ManifestFormat::Json => Self::parse_json(content)?,
+            ManifestFormat::Csv => Self::parse_delimited(content, b',')?,
+            ManifestFormat::Tsv => Self::parse_delimited(content, b'\t')?,
+        };
+
+        Ok(Self { entries })
+    }
+
+    pub fn detect_format(manifest_path: &Path, content: &str) -> ManifestFormat {
+        let has_sfv_extension = manifest_path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case(Self::SFV_EXTENSION));
+        if has_sfv_extension || content.starts_with(Self::SFV_COMMENT) {
+            return ManifestFormat::Sfv;
+        }
+
+        if HashdeepUtils::is_hashdeep(content) {
+            return ManifestFormat::Hashdeep;
+        }
+
+        let trimmed = content.trim_start();
+        if trimmed.starts_with('[') || trimmed.starts_with('{') {
+            return ManifestFormat::Json;
+        }
+
+        let first_line = trimmed.lines().next().unwrap_or_default();
+        if first_line.split(',').any(|c| c == "path") {
+            return ManifestFormat::Csv;
+        }
+        if first_line.split('\t').any(|c| c == "path") {
+            return ManifestFormat::Tsv;
+        }
+
+        if Self::parse_bsd_line(first_line).is_some() {
+            return ManifestFormat::Bsd;
+        }
+
+        ManifestFormat::Gnu
+    }
+
+    // SHA3-256SUMS, B3SUMS, foo.sha256, foo.b3
+    fn detect_algorithm(manifest_path: &Path) -> Option<Algorithm> {
+        let file_name = manifest_path.file_name()?.to_string_lossy().to_uppercase();
+        match file_name.strip_suffix(Self::SUMS_SUFFIX) {
+            Some(name) => Algorithm::from_name(name),
+            None => Algorithm::from_name(&manifest_path.extension()?.to_string_lossy()),
+        }
+    }
+
+    fn entry(path: &str, size: Option<u64>, hashes: &[(Algorithm, &str)]) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_owned(),
+            size,
+            hashes: hashes.iter().map(|(a, h)| (*a, h.to_lowercase())).collect(),
+        }
+    }
+
+    fn parse_gnu(
+        content: &str,
+        algorithm: Option<Algorithm>,
+    ) -> Result<Vec<ManifestEntry>, String> {
+        // hash  filename
+        // hash *filename
+        let mut entries = vec![];
+        for line in content.lines() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let space_pos = match line
+                .find(Self::DELIMITER)
+                .or_else(|| line.find(Self::BINARY_DELIMITER))
+            {
+                Some(n) => n,
+                None => {
+                    return Err("delimiter not found".to_string());
+                }
+            };
+
+            let hash = &line[0..space_pos];
+            let filename = &line[space_pos + Self::DELIMITER.len()..line.len()];
+            let algorithm = match Algorithm::from_hash_length(hash.len(), algorithm) {
+                Ok(a) => a,
+                Err(e) => {
+                    return Err(format!("{}  {}", e, filename));
+                }
+            };
+
+            entries.push(Self::entry(filename, None, &[(algorithm, hash)]));
+        }
+
+        Ok(entries)
+    }
+
+    fn parse_bsd_line(line: &str) -> Option<(Algorithm, &str, &str)> {
+        let (tag, rest) = line.split_once(" (")?;
+        let (filename, hash) = rest.rsplit_once(") = ")?;
+        let algorithm = Algorithm::from_name(tag)?;
+
+        Some((algorithm, filename, hash))
+    }
+
+    fn parse_bsd(content: &str) -> Result<Vec<ManifestEntry>, String> {
+        // ALGORITHM (filename) = hash
+        // the lines of the same filename are merged into an entry
+        let mut entries: Vec<ManifestEntry> = vec![];
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        for line in content.lines() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (algorithm, filename, hash) = match Self::parse_bsd_line(line) {
+                Some(t) => t,
+                None => {
+                    return Err(format!("invalid BSD tag line: {}", line));
+                }
+            };
+
+            match indices.get(filename) {
+                Some(i) => {
+                    entries[*i].hashes.insert(algorithm, hash.to_lowercase());
+                }
+                None => {
+                    indices.insert(filename.to_owned(), entries.len());
+                    entries.push(Self::entry(filename, None, &[(algorithm, hash)]));
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn parse_sfv(content: &str) -> Result<Vec<ManifestEntry>, String> {
+        // filename CRC32
+        // ; comment
+        let mut entries = vec![];
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with(Self::SFV_COMMENT) {
+                continue;
+            }
+
+            let (filename, hash) = match line.rsplit_once(' ') {
+                Some(p) => p,
+                None => {
+                    return Err("delimiter not found".to_string());
+                }
+            };
+
+            entries.push(Self::entry(
+                filename.trim_end(),
+                None,
+                &[(Algorithm::Crc32, hash)],
+            ));
+        }
+
+        Ok(entries)
+    }
+
+    fn parse_hashdeep(content: &str) -> Result<Vec<ManifestEntry>, String> {
+        let entries = HashdeepUtils::parse(content)?
+            .into_iter()
+            .map(|e| {
+                let hashes: Vec<(Algorithm, &str)> = [
+                    (Algorithm::Md5, &e.md5),
+                    (Algorithm::Sha1, &e.sha1),
+                    (Algorithm::Sha256, &e.sha256),
+                ]
+                .into_iter()
+                .filter_map(|(a, h)| h.as_deref().map(|h| (a, h)))
+                .collect();
+                Self::entry(&e.filename, e.size, &hashes)
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn parse_json_entry(value: &Value) -> Result<ManifestEntry, String> {
+        let path = match value.get("path").and_then(|p| p.as_str()) {
+            Some(p) => p,
+            None => {
+                return Err(format!("path not found: {}", value));
+            }
+        };
+
+        let mut hashes = vec![];
+        if let Some(h) = value.get("hashes").and_then(|h| h.as_object()) {
+            for (name, hash) in h.iter() {
+                if let (Some(a), Some(hash)) = (Algorithm::from_name(name), hash.as_str()) {
+                    hashes.push((a, hash));
+                }
+            }
+        }
+
+        let size = value.get("size").and_then(|s| s.as_u64());
+
+        Ok(Self::entry(path, size, &hashes))
+    }
+
+    fn parse_json(content: &str) -> Result<Vec<ManifestEntry>, String> {
+        // --output json is an array, --output ndjson is an object per line
+        let values: Vec<Value> = match serde_json::from_str(content) {
+            Ok(Value::Array(a)) => a,
+            Ok(v) => vec![v],
+            Err(_) => {
+                let mut values = vec![];
+                for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                    match serde_json::from_str(line) {
+                        Ok(v) => values.push(v),
+                        Err(e) => {
+                            return Err(format!("{}", e));
+                        }
+                    }
+                }
+                values
+            }
+        };
+
+        values.iter().map(Self::parse_json_entry).collect()
+    }
+
+    fn parse_delimited(content: &str, delimiter: u8) -> Result<Vec<ManifestEntry>, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(content.as_bytes());
+
+        let headers = match reader.headers() {
+            Ok(h) => h.clone(),
+            Err(e) => {
+                return Err(format!("{}", e));
+            }
+        };
+
+        let mut entries = vec![];
+        for row in reader.records() {
+            let row = match row {
+                Ok(r) => r,
+                Err(e) => {
+                    return Err(format!("{}", e));
+                }
+            };
+
+            let mut path = None;
+            let mut size = None;
+            let mut hashes = vec![];
+            for (column, value) in headers.iter().zip(row.iter()) {
+                if value.is_empty() {
+                    continue;
+                }
+
+                match column {
+                    "path" => path = Some(value),
+                    "size" => size = value.parse().ok(),
+                    c => {
+                        if let Some(a) = Algorithm::from_name(c) {
+                            hashes.push((a, value));
+                        }
+                    }
+                }
+            }
+
+            match path {
+                Some(p) => entries.push(Self::entry(p, size, &hashes)),
+                None => {
+                    return Err("path column not found".to_string());
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // replace the leading FROM of each path with TO
+    pub fn rebase(&mut self, from: &Path, to: &Path) {
+        for entry in self.entries.iter_mut() {
+            if let Ok(rest) = Path::new(&entry.path).strip_prefix(from) {
+                entry.path = to.join(rest).to_string_lossy().to_string();
+            }
+        }
+    }
+
+    pub fn write(&self, format: ManifestFormat) -> Result<String, String> {
+        let lines = match format {
+            ManifestFormat::Gnu => self.write_gnu()?,
+            ManifestFormat::Bsd => self.write_bsd(),
+            ManifestFormat::Sfv => self.write_sfv()?,
+            ManifestFormat::Hashdeep => self.write_hashdeep(),
+            ManifestFormat::Json => {
+                let records: Vec<Record> = self.entries.iter().map(|e| e.to_record()).collect();
+                vec![serde_json::to_string_pretty(&records).unwrap()]
+            }
+            ManifestFormat::Csv => self.write_delimited(b',')?,
+            ManifestFormat::Tsv => self.write_delimited(b'\t')?,
+        };
+
+        Ok(lines.iter().map(|l| format!("{}\n", l)).collect())
+    }
+
+    fn write_gnu(&self) -> Result<Vec<String>, String> {
+        let mut lines = vec![];
+        for entry in self.entries.iter() {
+            match entry.strongest_hash() {
+                // the algorithm of these cannot be told from the length of the hash
+                Some((a @ (Algorithm::Sha3_256 | Algorithm::Blake3), _)) => {
+                    return Err(format!(
+                        "{} hash cannot be written in GNU format, use bsd: {}",
+                        a.name(),
+                        entry.path
+                    ));
+                }
+                Some((_, h)) => lines.push(format!("{}{}{}", h, Self::DELIMITER, entry.path)),
+                None => {
+                    return Err(format!("no supported hash: {}", entry.path));
+                }
+            }
+        }
+
+        Ok(lines)
+    }
+
+    fn write_bsd(&self) -> Vec<String> {
+        let mut lines = vec![];
+        for entry in self.entries.iter() {
+            for (algorithm, hash) in entry.hashes.iter() {
+                lines.push(format!(
+                    "{} ({}) = {}",
+                    algorithm.name().to_uppercase(),
+                    entry.path,
+                    hash
+                ));
+            }
+        }
+
+        lines
+    }
+
+    fn sfv_header() -> String {
+        format!(
+            "{} Generated by {} v{}",
+            Self::SFV_COMMENT,
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+
+    fn write_sfv(&self) -> Result<Vec<String>, String> {
+        let mut lines = vec![Self::sfv_header()];
+        for entry in self.entries.iter() {
+            match entry.hashes.get(&Algorithm::Crc32) {
+                Some(h) => lines.push(format!("{} {}", entry.path, h.to_uppercase())),
+                None => {
+                    return Err(format!("no crc32 hash: {}", entry.path));
+                }
+            }
+        }
+
+        Ok(lines)
+    }
+
+    fn write_hashdeep(&self) -> Vec<String> {
+        let invoked_from = env::current_dir().unwrap_or_default();
+        let command_line = env::args().collect::<Vec<String>>().join(" ");
+
+        let mut lines = vec![HashdeepUtils::format_header(&invoked_from, &command_line)];
+        for entry in self.entries.iter() {
+            let hashdeep_entry = HashdeepEntry {
+                size: entry.size,
+                md5: entry.hashes.get(&Algorithm::Md5).cloned(),
+                sha1: entry.hashes.get(&Algorithm::Sha1).cloned(),
+                sha256: entry.hashes.get(&Algorithm::Sha256).cloned(),
+                filename: entry.path.clone(),
+            };
+            lines.push(HashdeepUtils::format_line(&hashdeep_entry));
+        }
+
+        lines
+    }
+
+    fn write_delimited(&self, delimiter: u8) -> Result<Vec<String>, String> {
+        // path, size and the algorithms used in any entry
+        let algorithms: Vec<Algorithm> = Algorithm::ALL
+            .into_iter()
+            .filter(|a| self.entries.iter().any(|e| e.hashes.contains_key(a)))
+            .collect();
+
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(vec![]);
+
+        let mut header = vec!["path".to_string(), "size".to_string()];
+        header.extend(algorithms.iter().map(|a| a.name().to_string()));
+        let mut rows = vec![header];
+        for entry in self.entries.iter() {
+            let mut row = vec![
+                entry.path.clone(),
+                entry.size.map(|s| s.to_string()).unwrap_or_default(),
+            ];
+            row.extend(
+                algorithms
+                    .iter()
+                    .map(|a| entry.hashes.get(a).cloned().unwrap_or_default()),
+            );
+            rows.push(row);
+        }
+
+        for row in rows.iter() {
+            if let Err(e) = writer.write_record(row) {
+                return Err(format!("{}", e));
+            }
+        }
+
+        let bytes = match writer.into_inner() {
+            Ok(b) => b,
+            Err(e) => {
+                return Err(format!("{}", e));
+            }
+        };
+
+        // a quoted path may contain a newline
+        Ok(vec![String::from_utf8_lossy(&bytes)
+            .trim_end_matches('\n')
+            .to_string()])
+    }
+}
@@ -1,21 +1,31 @@
 use crypto::{digest::Digest, md5::Md5};
 
-use crate::hashes::hash::Hash;
+use crate::hashes::hash::{Hash, HashState};
 
 pub struct Md5Hash {}
 
 impl Hash for Md5Hash {
-    fn calc(value: impl Into<String>) -> String {
-        Self::calc_bytes(value.into().as_bytes())
-    }
-
     fn calc_bytes(bytes: &[u8]) -> String {
         let mut md5 = Md5::new();
         md5.input(bytes);
         md5.result_str()
     }
 
+    fn state() -> Box<dyn HashState> {
+        Box::new(Md5::new())
+    }
+
     fn get_hash_length() -> usize {
         32
     }
 }
+
+impl HashState for Md5 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.input(bytes);
+    }
+
+    fn finish(mut self: Box<Self>) -> String {
+        self.result_str()
+    }
+}
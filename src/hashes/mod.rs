@@ -1,4 +1,10 @@
+pub mod algorithm;
+pub mod blake3;
+pub mod crc32;
 pub mod hash;
+pub mod hashdeep;
+pub mod manifest;
 pub mod md5;
 pub mod sha1;
 pub mod sha256;
+pub mod sha3_256;
@@ -1,6 +1,6 @@
 use crypto::{digest::Digest, sha1::Sha1};
 
-use crate::hashes::hash::Hash;
+use crate::hashes::hash::{Hash, HashState};
 
 pub struct Sha1Hash {}
 
@@ -11,7 +11,21 @@ impl Hash for Sha1Hash {
         sha1.result_str()
     }
 
+    fn state() -> Box<dyn HashState> {
+        Box::new(Sha1::new())
+    }
+
     fn get_hash_length() -> usize {
         40
     }
 }
+
+impl HashState for Sha1 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.input(bytes);
+    }
+
+    fn finish(mut self: Box<Self>) -> String {
+        self.result_str()
+    }
+}
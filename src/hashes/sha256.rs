@@ -1,6 +1,6 @@
 use crypto::{digest::Digest, sha2::Sha256};
 
-use crate::hashes::hash::Hash;
+use crate::hashes::hash::{Hash, HashState};
 
 pub struct Sha256Hash {}
 
@@ -11,7 +11,21 @@ impl Hash for Sha256Hash {
         sha256.result_str()
     }
 
+    fn state() -> Box<dyn HashState> {
+        Box::new(Sha256::new())
+    }
+
     fn get_hash_length() -> usize {
         64
     }
 }
+
+impl HashState for Sha256 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.input(bytes);
+    }
+
+    fn finish(mut self: Box<Self>) -> String {
+        self.result_str()
+    }
+}
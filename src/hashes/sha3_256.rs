@@ -0,0 +1,31 @@
+use crypto::{digest::Digest, sha3::Sha3};
+
+use crate::hashes::hash::{Hash, HashState};
+
+pub struct Sha3_256Hash {}
+
+impl Hash for Sha3_256Hash {
+    fn calc_bytes(bytes: &[u8]) -> String {
+        let mut sha3 = Sha3::sha3_256();
+        sha3.input(bytes);
+        sha3.result_str()
+    }
+
+    fn state() -> Box<dyn HashState> {
+        Box::new(Sha3::sha3_256())
+    }
+
+    fn get_hash_length() -> usize {
+        64
+    }
+}
+
+impl HashState for Sha3 {
+    fn update(&mut self, bytes: &[u8]) {
+        self.input(bytes);
+    }
+
+    fn finish(mut self: Box<Self>) -> String {
+        self.result_str()
+    }
+}
@@ -1,17 +1,24 @@
-use std::process::ExitCode;
+use std::{path::PathBuf, process::ExitCode};
 
-use clap::{arg, command, Parser};
+use clap::Parser;
 
 mod db;
 mod models;
+mod openpgp;
 
+use crate::hashes::algorithm::Algorithm;
 use crate::hashes::hash::ChecksumFileUtils;
+use crate::hashes::manifest::ManifestFormat;
 
 mod hashes;
 mod modes;
+mod output;
+mod signature;
 mod utils;
 
+use crate::modes::dedupe_db::LinkType;
 use crate::modes::utils::determine_mode;
+use crate::output::{Column, OutputFormat};
 
 #[derive(Clone, Parser, Debug)]
 #[command(author, version, about, long_about=None)]
@@ -28,12 +35,108 @@ struct Args {
     #[arg(long = "sha256", default_value = "false", help = "show sha256 hash")]
     sha256: bool,
 
+    #[arg(
+        long = "sfv",
+        default_value = "false",
+        help = "print the crc32 hashes of the FILEs in SFV format"
+    )]
+    sfv: bool,
+
+    #[arg(
+        long = "hashdeep",
+        default_value = "false",
+        help = "print the hashes of the FILEs in hashdeep format (directories are searched recursively)"
+    )]
+    hashdeep: bool,
+
+    #[arg(
+        long = "audit",
+        value_name = "KNOWN",
+        help = "audit the FILEs (default: current directory) against the KNOWN hashdeep file"
+    )]
+    audit_known_filepath: Option<String>,
+
+    #[arg(
+        long = "sidecar",
+        value_name = "ALGORITHM",
+        value_delimiter = ',',
+        help = "write a sidecar checksum file (e.g., FILE.sha256) next to each of the FILEs"
+    )]
+    sidecar_algorithms: Option<Vec<Algorithm>>,
+
+    #[arg(
+        long = "check-sidecar",
+        default_value = "false",
+        help = "check the sidecar checksum files of the FILEs (default: current directory)"
+    )]
+    check_sidecar: bool,
+
+    #[arg(
+        long = "convert",
+        value_name = "FORMAT",
+        value_enum,
+        help = "convert the checksum FILEs (default: stdin) to the FORMAT"
+    )]
+    convert_format: Option<ManifestFormat>,
+
+    #[arg(
+        long = "rebase",
+        value_name = "FROM=TO",
+        help = "replace the leading FROM of each path with TO on --convert"
+    )]
+    rebase: Option<String>,
+
+    #[arg(
+        long = "diff",
+        num_args = 2,
+        value_names = ["OLD", "NEW"],
+        help = "print the entries added, removed, changed and renamed from the OLD checksum file to the NEW one"
+    )]
+    diff_filepaths: Option<Vec<String>>,
+
+    #[arg(
+        long = "algo",
+        value_name = "ALGORITHM",
+        value_enum,
+        help = "the algorithm of the hashes that have the same length as others (e.g., sha256, sha3-256 and blake3)"
+    )]
+    algorithm: Option<Algorithm>,
+
+    #[arg(
+        long = "expect",
+        value_name = "DIGEST",
+        help = "check that the FILEs (default: stdin) have the DIGEST (hex, base64, ALGORITHM:DIGEST or SRI)"
+    )]
+    expected_digest: Option<String>,
+
     #[arg(
         short = 'c',
         long = "check",
-        help = "read checksums from the FILEs and check them"
+        num_args = 1..,
+        help = "read checksums from the FILEs and check them (\"-\" reads from stdin)"
+    )]
+    checksum_filepaths: Option<Vec<String>>,
+
+    #[arg(
+        long = "pubkey",
+        value_name = "KEY",
+        help = "verify the signature (FILE.minisig or FILE.sig) of each checksum file with the minisign or signify public KEY on --check"
+    )]
+    public_key: Option<String>,
+
+    #[arg(
+        long = "keyring",
+        value_name = "KEYRING",
+        help = "verify the OpenPGP clear-signed checksum files with the public keys in the KEYRING file on --check"
+    )]
+    keyring_filepath: Option<String>,
+
+    #[arg(
+        long = "sign",
+        value_name = "SECKEY",
+        help = "sign the FILEs with the minisign or signify secret key SECKEY (writes FILE.minisig or FILE.sig)"
     )]
-    checksum_filepath: Option<String>,
+    secret_key_filepath: Option<String>,
 
     #[arg(
         long = "ignore-missing",
@@ -42,6 +145,36 @@ struct Args {
     )]
     ignore_missing: bool,
 
+    #[arg(
+        long = "unlisted",
+        value_name = "DIR",
+        help = "report the files in the DIR that are not listed in the checksum files on --check"
+    )]
+    unlisted_dir: Option<String>,
+
+    #[arg(
+        long = "strict",
+        default_value = "false",
+        help = "exit non-zero if any unlisted file is found on --unlisted"
+    )]
+    strict: bool,
+
+    #[arg(
+        short = 'j',
+        long = "jobs",
+        value_name = "N",
+        help = "verify N files in parallel on --check (default: number of CPUs)"
+    )]
+    jobs: Option<usize>,
+
+    #[arg(
+        long = "db",
+        value_name = "PATH",
+        env = "RHH_DB",
+        help = "hash DB file (default: hash_table.db)"
+    )]
+    db_filepath: Option<PathBuf>,
+
     #[arg(
         long = "init-db",
         default_value = "false",
@@ -56,8 +189,162 @@ struct Args {
     )]
     update_database: bool,
 
+    #[arg(
+        long = "paranoid",
+        default_value = "false",
+        help = "rehash all files on --update-db, even if their size, mtime, ctime and inode are unchanged"
+    )]
+    paranoid: bool,
+
+    #[arg(
+        long = "prune",
+        default_value = "false",
+        help = "delete the records of files that no longer exist on --update-db"
+    )]
+    prune: bool,
+
     #[arg(short = 'd', long = "use-db", help = "use hash database")]
     use_db: bool,
+
+    #[arg(
+        long = "audit-db",
+        default_value = "false",
+        help = "scan the roots stored in database and compare their files with it without updating it"
+    )]
+    audit_database: bool,
+
+    #[arg(
+        long = "scrub-db",
+        default_value = "false",
+        help = "rehash the files in database to detect silent corruption"
+    )]
+    scrub_database: bool,
+
+    #[arg(
+        long = "sample",
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        conflicts_with = "oldest",
+        requires = "scrub_database",
+        help = "scrub a random PERCENT of the files"
+    )]
+    sample: Option<u8>,
+
+    #[arg(
+        long = "oldest",
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        requires = "scrub_database",
+        help = "scrub the PERCENT of the files verified least recently"
+    )]
+    oldest: Option<u8>,
+
+    #[arg(
+        long = "rebind",
+        value_name = "NAME=PATH",
+        help = "bind the root NAME in database to PATH, e.g. where the same files are mounted"
+    )]
+    rebind_root: Option<String>,
+
+    #[arg(
+        long = "find-db",
+        default_value = "false",
+        help = "print the files in database that have the hashes given as FILEs (default: stdin)"
+    )]
+    find_database: bool,
+
+    #[arg(
+        long = "duplicates-db",
+        default_value = "false",
+        help = "print the files in database that have the same SHA256, most wasted space first"
+    )]
+    duplicates_database: bool,
+
+    #[arg(
+        long = "same-size",
+        default_value = "false",
+        requires = "duplicates_database",
+        help = "group duplicates by size as well as SHA256"
+    )]
+    same_size: bool,
+
+    #[arg(
+        long = "dedupe-db",
+        default_value = "false",
+        help = "replace the duplicates in database with links to one of them, after comparing their bytes"
+    )]
+    dedupe_database: bool,
+
+    #[arg(
+        long = "link",
+        value_enum,
+        default_value_t = LinkType::Hard,
+        requires = "dedupe_database",
+        help = "how duplicates are replaced, reflink is supported on btrfs and XFS"
+    )]
+    link: LinkType,
+
+    #[arg(
+        long = "dry-run",
+        default_value = "false",
+        requires = "dedupe_database",
+        help = "print the duplicates to replace without replacing them"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long = "undo-log",
+        value_name = "PATH",
+        requires = "dedupe_database",
+        help = "append the replaced files to PATH (default: dedupe_undo.log)"
+    )]
+    undo_log: Option<PathBuf>,
+
+    #[arg(
+        long = "undo-dedupe",
+        value_name = "PATH",
+        conflicts_with = "dedupe_database",
+        help = "give the files replaced by --dedupe-db their own copies again, as recorded in the undo log PATH"
+    )]
+    undo_dedupe: Option<PathBuf>,
+
+    #[arg(
+        long = "export-db",
+        default_value = "false",
+        help = "print file and hash records in database"
+    )]
+    export_database: bool,
+
+    #[arg(
+        long = "output",
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "output format"
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long = "columns",
+        value_enum,
+        value_delimiter = ',',
+        help = "columns of the csv and tsv output (default: path,size,mtime,md5,sha1,sha256)"
+    )]
+    columns: Option<Vec<Column>>,
+
+    #[arg(
+        long = "format",
+        value_name = "TEMPLATE",
+        help = "print each record by the TEMPLATE (e.g., '{sha256}  {size}  {path}')"
+    )]
+    format: Option<String>,
+
+    #[arg(
+        short = 'z',
+        long = "zero",
+        default_value = "false",
+        help = "end each record printed by the TEMPLATE with NUL, not newline"
+    )]
+    zero: bool,
 }
 
 fn main() -> ExitCode {
@@ -1,46 +1,43 @@
-use std::rc::Rc;
+use std::{fs, path::Path, rc::Rc, time::UNIX_EPOCH};
 
-use rusqlite::Connection;
+use rusqlite::{params, Connection, Row};
 
-use crate::db::is_sqlite_error_constraint_violation;
+use crate::{db::is_sqlite_error_constraint_violation, utils::ctime_and_inode};
 
 use super::model::Model;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FileTable {
     pub id: Option<i64>,
     pub full_path: String,
     pub file_name: String,
+    // the metadata when the file was hashed, to skip unchanged files on update
+    // (mtime and ctime are in nanoseconds since the UNIX epoch)
+    pub size: Option<i64>,
+    pub mtime: Option<i64>,
+    pub ctime: Option<i64>,
+    pub inode: Option<i64>,
+    // when the hashes were last verified, in seconds since the UNIX epoch
+    pub verified: Option<i64>,
+    // full_path is relative to the root, or to the current directory if None
+    pub root_id: Option<i64>,
 }
 
 impl Model for FileTable {
     fn create(connection: &Connection) {
-        static SQL: &str = r#"
-        CREATE TABLE IF NOT EXISTS files (
-            id INTEGER PRIMARY KEY,
-            full_path TEXT NOT NULL UNIQUE,
-            file_name TEXT NOT NULL
-        );
-        "#;
-
-        connection.execute(SQL, []).unwrap();
+        connection.execute(&Self::create_sql("files"), []).unwrap();
+        Self::add_metadata_columns(connection);
+        Self::add_root_column(connection);
     }
 
     fn get(connection: &Connection, id: i64) -> Self {
         static SQL: &str = "SELECT * FROM files WHERE id = ?";
 
         let mut stmt = connection.prepare(SQL).unwrap();
-        let mut rows = stmt.query(&[&id]).unwrap();
+        let mut rows = stmt.query([&id]).unwrap();
         let row = rows.next().unwrap().unwrap();
 
-        let full_path = row.get(1).unwrap();
-        let file_name = row.get(2).unwrap();
-
-        Self {
-            id: Some(id),
-            full_path,
-            file_name,
-        }
+        Self::from_row(row)
     }
 
     fn all(connection: &Connection) -> Vec<Rc<Self>> {
@@ -50,14 +47,7 @@ impl Model for FileTable {
         let mut rows = stmt.query([]).unwrap();
         let mut files = Vec::new();
         while let Some(row) = rows.next().unwrap() {
-            let id = row.get(0).unwrap();
-            let full_path = row.get(1).unwrap();
-            let file_name = row.get(2).unwrap();
-            files.push(Rc::new(Self {
-                id: Some(id),
-                full_path,
-                file_name,
-            }))
+            files.push(Rc::new(Self::from_row(row)))
         }
 
         files
@@ -65,16 +55,26 @@ impl Model for FileTable {
 
     fn insert(&self, connection: &Connection) -> i64 {
         static INSERT_SQL: &str = r#"
-            INSERT INTO files (full_path, file_name)
-            VALUES (?,?)
+            INSERT INTO files (full_path, file_name, size, mtime, ctime, inode, verified, root_id)
+            VALUES (?,?,?,?,?,?,?,?)
         "#;
 
         let mut stmt = connection.prepare(INSERT_SQL).unwrap();
-        match stmt.execute([&self.full_path, &self.file_name]) {
+        match stmt.execute(params![
+            &self.full_path,
+            &self.file_name,
+            self.size,
+            self.mtime,
+            self.ctime,
+            self.inode,
+            self.verified,
+            self.root_id
+        ]) {
             Ok(_) => connection.last_insert_rowid(),
             Err(e) => {
                 if is_sqlite_error_constraint_violation(&e) {
-                    let duplicated_id = Self::get_id_by_full_path(connection, &self.full_path);
+                    let duplicated_id =
+                        Self::get_id_by_path(connection, self.root_id, &self.full_path);
                     return duplicated_id;
                 }
 
@@ -86,14 +86,22 @@ impl Model for FileTable {
 
     fn update(&self, connection: &Connection) -> i64 {
         static UPDATE_SQL: &str = r#"
-            UPDATE files SET full_path=?, file_name=? WHERE id=?
+            UPDATE files
+            SET full_path=?, file_name=?, size=?, mtime=?, ctime=?, inode=?, verified=?, root_id=?
+            WHERE id=?
         "#;
 
         let mut stmt = connection.prepare(UPDATE_SQL).unwrap();
-        stmt.execute([
+        stmt.execute(params![
             &self.full_path,
             &self.file_name,
-            &format!("{}", self.id.unwrap()),
+            self.size,
+            self.mtime,
+            self.ctime,
+            self.inode,
+            self.verified,
+            self.root_id,
+            self.id.unwrap()
         ])
         .unwrap();
 
@@ -111,17 +119,184 @@ impl Model for FileTable {
 }
 
 impl FileTable {
-    pub fn get_id_by_full_path(connection: &Connection, full_path: impl Into<String>) -> i64 {
+    pub fn find_by_path(
+        connection: &Connection,
+        root_id: Option<i64>,
+        full_path: &str,
+    ) -> Option<Self> {
+        static SQL: &str = "SELECT * FROM files WHERE root_id=? AND full_path=?";
+        // DB created by older versions may have no root_id column
+        static LEGACY_SQL: &str = "SELECT * FROM files WHERE full_path=?";
+
+        let (mut stmt, params) = match root_id {
+            Some(_) => (
+                connection.prepare(SQL).unwrap(),
+                params![root_id, full_path],
+            ),
+            None => (connection.prepare(LEGACY_SQL).unwrap(), params![full_path]),
+        };
+        let mut rows = stmt.query(params).unwrap();
+        let row = rows.next().unwrap()?;
+
+        Some(Self::from_row(row))
+    }
+
+    // the record of the file on the filesystem, with the current metadata
+    pub fn from_path(path: &Path) -> Self {
+        let metadata = fs::metadata(path).ok();
+        let size = metadata.as_ref().map(|m| m.len() as i64);
+        let mtime = metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos() as i64);
+        let (ctime, inode) = match metadata {
+            Some(ref m) => ctime_and_inode(m),
+            None => (None, None),
+        };
+
+        Self {
+            id: None,
+            full_path: path.to_string_lossy().to_string(),
+            file_name: path.file_name().unwrap().to_string_lossy().to_string(),
+            size,
+            mtime,
+            ctime,
+            inode,
+            verified: None,
+            root_id: None,
+        }
+    }
+
+    // the records of old DB have no metadata, so they are never the same
+    pub fn same_metadata(&self, other: &Self) -> bool {
+        self.size.is_some()
+            && self.mtime.is_some()
+            && (self.size, self.mtime, self.ctime, self.inode)
+                == (other.size, other.mtime, other.ctime, other.inode)
+    }
+
+    // DB opened read-only cannot have the columns added
+    fn from_row(row: &Row) -> Self {
+        Self {
+            id: Some(row.get(0).unwrap()),
+            full_path: row.get(1).unwrap(),
+            file_name: row.get(2).unwrap(),
+            size: row.get(3).unwrap_or(None),
+            mtime: row.get(4).unwrap_or(None),
+            ctime: row.get(5).unwrap_or(None),
+            inode: row.get(6).unwrap_or(None),
+            verified: row.get(7).unwrap_or(None),
+            root_id: row.get(8).unwrap_or(None),
+        }
+    }
+
+    // a random N% of the files
+    pub fn sample(connection: &Connection, percent: u8) -> Vec<Self> {
+        Self::select(connection, "ORDER BY RANDOM()", percent)
+    }
+
+    // the N% of the files verified least recently, never verified first
+    pub fn least_recently_verified(connection: &Connection, percent: u8) -> Vec<Self> {
+        Self::select(
+            connection,
+            "ORDER BY verified IS NOT NULL, verified",
+            percent,
+        )
+    }
+
+    fn select(connection: &Connection, order: &str, percent: u8) -> Vec<Self> {
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))
+            .unwrap();
+        // at least one file
+        let limit = (count * percent as i64 + 99) / 100;
+
+        let sql = format!("SELECT * FROM files {} LIMIT ?", order);
+        let mut stmt = connection.prepare(&sql).unwrap();
+        let mut rows = stmt.query([limit]).unwrap();
+        let mut files = Vec::new();
+        while let Some(row) = rows.next().unwrap() {
+            files.push(Self::from_row(row))
+        }
+
+        files
+    }
+
+    fn create_sql(table_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY,
+                full_path TEXT NOT NULL,
+                file_name TEXT NOT NULL,
+                size INTEGER,
+                mtime INTEGER,
+                ctime INTEGER,
+                inode INTEGER,
+                verified INTEGER,
+                root_id INTEGER,
+                FOREIGN KEY (root_id) REFERENCES roots (id)
+                UNIQUE(root_id, full_path)
+            );
+            "#,
+            table_name
+        )
+    }
+
+    // full_path of DB created by older versions is unique by itself,
+    // so the table is rebuilt to make it unique per root
+    fn add_root_column(connection: &Connection) {
+        let mut stmt = connection.prepare("PRAGMA table_info(files)").unwrap();
+        let has_root: bool = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .filter_map(|c| c.ok())
+            .any(|c| c == "root_id");
+        if has_root {
+            return;
+        }
+
+        let sql = format!(
+            r#"
+            BEGIN;
+            {}
+            INSERT INTO files_new SELECT *, NULL FROM files;
+            DROP TABLE files;
+            ALTER TABLE files_new RENAME TO files;
+            COMMIT;
+            "#,
+            Self::create_sql("files_new")
+        );
+        connection.execute_batch(&sql).unwrap();
+    }
+
+    // DB created by older versions has no metadata columns
+    fn add_metadata_columns(connection: &Connection) {
+        let mut stmt = connection.prepare("PRAGMA table_info(files)").unwrap();
+        let columns: Vec<String> = stmt
+            .query_map([], |row| row.get(1))
+            .unwrap()
+            .filter_map(|c| c.ok())
+            .collect();
+
+        for column in ["size", "mtime", "ctime", "inode", "verified"] {
+            if !columns.iter().any(|c| c == column) {
+                let sql = format!("ALTER TABLE files ADD COLUMN {} INTEGER", column);
+                connection.execute(&sql, []).unwrap();
+            }
+        }
+    }
+
+    pub fn get_id_by_path(connection: &Connection, root_id: Option<i64>, full_path: &str) -> i64 {
         static SQL: &str = r#"
-            SELECT id FROM files WHERE full_path=?
+            SELECT id FROM files WHERE root_id IS ? AND full_path=?
         "#;
 
         let mut stmt = connection.prepare(SQL).unwrap();
-        let mut rows = stmt.query(&[&full_path.into()]).unwrap();
+        let mut rows = stmt.query(params![root_id, full_path]).unwrap();
 
         let row = rows.next().unwrap().unwrap();
-        let id = row.get(0).unwrap();
-
-        id
+        row.get(0).unwrap()
     }
 }
@@ -31,7 +31,7 @@ impl Model for Md5HashTable {
         static SQL: &str = "SELECT * FROM md5_hash_table WHERE id = ?";
 
         let mut stmt = connection.prepare(SQL).unwrap();
-        let mut rows = stmt.query(&[&id]).unwrap();
+        let mut rows = stmt.query([&id]).unwrap();
         let row = rows.next().unwrap().unwrap();
 
         let id = row.get(0).unwrap();
@@ -73,7 +73,7 @@ impl Model for Md5HashTable {
 
         let mut stmt = connection.prepare(INSERT_SQL).unwrap();
         match stmt.execute([&format!("{}", self.file_id), &self.hash]) {
-            Ok(_) => return connection.last_insert_rowid(),
+            Ok(_) => connection.last_insert_rowid(),
             Err(e) => {
                 if db::is_sqlite_error_constraint_violation(&e) {
                     let duplicated_id = Self::get_id_by_file_id(connection, self.file_id);
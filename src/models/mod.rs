@@ -1,5 +1,6 @@
 pub mod file_table;
 pub mod md5_hash_table;
 pub mod model;
+pub mod root_table;
 pub mod sha1_hash_table;
 pub mod sha256_hash_table;
@@ -0,0 +1,126 @@
+use std::rc::Rc;
+
+use rusqlite::{Connection, Row};
+
+use crate::db::is_sqlite_error_constraint_violation;
+
+use super::model::Model;
+
+// the directories and files scanned by --init-db and --update-db
+// (the paths of the files are stored relative to their root)
+#[derive(Clone, Debug)]
+pub struct RootTable {
+    pub id: Option<i64>,
+    pub name: String,
+    pub path: String,
+}
+
+impl Model for RootTable {
+    fn create(connection: &Connection) {
+        static SQL: &str = r#"
+        CREATE TABLE IF NOT EXISTS roots (
+            id INTEGER PRIMARY KEY,
+            path TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL UNIQUE
+        );
+        "#;
+
+        connection.execute(SQL, []).unwrap();
+    }
+
+    fn get(connection: &Connection, id: i64) -> Self {
+        static SQL: &str = "SELECT id, name, path FROM roots WHERE id = ?";
+
+        let mut stmt = connection.prepare(SQL).unwrap();
+        let mut rows = stmt.query([&id]).unwrap();
+        let row = rows.next().unwrap().unwrap();
+
+        Self::from_row(row)
+    }
+
+    fn all(connection: &Connection) -> Vec<Rc<Self>> {
+        static SQL: &str = "SELECT id, name, path FROM roots ORDER BY id";
+
+        let mut stmt = connection.prepare(SQL).unwrap();
+        let mut rows = stmt.query([]).unwrap();
+        let mut roots = Vec::new();
+        while let Some(row) = rows.next().unwrap() {
+            roots.push(Rc::new(Self::from_row(row)))
+        }
+
+        roots
+    }
+
+    fn insert(&self, connection: &Connection) -> i64 {
+        static INSERT_SQL: &str = r#"
+            INSERT INTO roots (name, path)
+            VALUES (?, ?)
+        "#;
+
+        let mut stmt = connection.prepare(INSERT_SQL).unwrap();
+        match stmt.execute([&self.name, &self.path]) {
+            Ok(_) => connection.last_insert_rowid(),
+            Err(e) => {
+                if is_sqlite_error_constraint_violation(&e) {
+                    return Self::get_id_by_path(connection, &self.path);
+                }
+
+                eprintln!("RootTable insert failed. {:?}", e);
+                -1
+            }
+        }
+    }
+
+    fn update(&self, connection: &Connection) -> i64 {
+        static UPDATE_SQL: &str = r#"
+            UPDATE roots SET name=?, path=? WHERE id=?
+        "#;
+
+        let mut stmt = connection.prepare(UPDATE_SQL).unwrap();
+        stmt.execute((&self.name, &self.path, self.id.unwrap()))
+            .unwrap();
+
+        self.id.unwrap()
+    }
+
+    fn delete(&self, connection: &Connection) {
+        static DELETE_SQL: &str = r#"
+            DELETE FROM roots WHERE id=?
+        "#;
+
+        let mut stmt = connection.prepare(DELETE_SQL).unwrap();
+        stmt.execute([self.id.unwrap()]).unwrap();
+    }
+}
+
+impl RootTable {
+    pub fn find_by_name(connection: &Connection, name: &str) -> Option<Self> {
+        static SQL: &str = "SELECT id, name, path FROM roots WHERE name=?";
+
+        let mut stmt = connection.prepare(SQL).unwrap();
+        let mut rows = stmt.query([name]).unwrap();
+        let row = rows.next().unwrap()?;
+
+        Some(Self::from_row(row))
+    }
+
+    pub fn get_id_by_path(connection: &Connection, path: &str) -> i64 {
+        static SQL: &str = r#"
+            SELECT id FROM roots WHERE path=?
+        "#;
+
+        let mut stmt = connection.prepare(SQL).unwrap();
+        let mut rows = stmt.query([path]).unwrap();
+
+        let row = rows.next().unwrap().unwrap();
+        row.get(0).unwrap()
+    }
+
+    fn from_row(row: &Row) -> Self {
+        Self {
+            id: Some(row.get(0).unwrap()),
+            name: row.get(1).unwrap(),
+            path: row.get(2).unwrap(),
+        }
+    }
+}
@@ -29,7 +29,7 @@ impl Model for Sha256HashTable {
         static SQL: &str = "SELECT * FROM sha256_hash_table WHERE id = ?";
 
         let mut stmt = connection.prepare(SQL).unwrap();
-        let mut rows = stmt.query(&[&id]).unwrap();
+        let mut rows = stmt.query([&id]).unwrap();
         let row = rows.next().unwrap().unwrap();
 
         let id = row.get(0).unwrap();
@@ -71,7 +71,7 @@ impl Model for Sha256HashTable {
 
         let mut stmt = connection.prepare(INSERT_SQL).unwrap();
         match stmt.execute([&format!("{}", self.file_id), &self.hash]) {
-            Ok(_) => return connection.last_insert_rowid(),
+            Ok(_) => connection.last_insert_rowid(),
             Err(e) => {
                 if db::is_sqlite_error_constraint_violation(&e) {
                     let duplicated_id = Self::get_id_by_file_id(connection, self.file_id);
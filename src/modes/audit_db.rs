@@ -0,0 +1,156 @@
+use std::{collections::HashSet, path::PathBuf, process::ExitCode};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{
+    db::{find_hashes_by_file_id, rehash_stored, resolve_path, scan_roots, stored_roots},
+    models::{file_table::FileTable, model::Model},
+    output::{owned_hash_map, Output, OutputOptions, Record},
+};
+
+use super::utils::Mode;
+
+pub struct AuditDatabaseMode {
+    pub db_path: PathBuf,
+    pub output: OutputOptions,
+}
+
+#[derive(Debug, Default)]
+struct AuditSummary {
+    unchanged: usize,
+    modified: usize,
+    metadata_changed: usize,
+    new: usize,
+    missing: usize,
+}
+
+impl Mode for AuditDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        // the audit never writes to DB
+        let connection =
+            match Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 255.into();
+                }
+            };
+
+        let roots = stored_roots(&connection);
+        let file_list = match scan_roots(&roots, db_path) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        // the stored record of each file, or None if it is not in DB
+        let located: Vec<Option<FileTable>> = file_list
+            .iter()
+            .map(|f| FileTable::find_by_path(&connection, f.root_id, &f.full_path))
+            .collect();
+        let stored_files = located
+            .iter()
+            .zip(file_list.iter())
+            .filter_map(|(s, f)| Some((s.clone()?, f.path.clone())))
+            .collect();
+        let mut rehashed = rehash_stored(&connection, stored_files).into_iter();
+
+        let mut summary = AuditSummary::default();
+        let mut seen: HashSet<(Option<i64>, String)> = HashSet::new();
+        let mut failed = false;
+        for (scanned, located) in file_list.iter().zip(located) {
+            let file = &scanned.path;
+            let rehashed = match located {
+                Some(_) => rehashed.next().unwrap(),
+                None => {
+                    summary.new += 1;
+                    let record = Record {
+                        status: Some("NEW".to_string()),
+                        ..Record::for_file(file)
+                    };
+                    output.emit(record, |_| println!("{}: NEW", file.display()));
+                    continue;
+                }
+            };
+            let stored = &rehashed.file;
+            seen.insert((stored.root_id, stored.full_path.clone()));
+
+            let actual = match rehashed.actual {
+                Ok(a) => a,
+                Err(e) => {
+                    output.emit(Record::error(file.to_string_lossy(), &e), |_| {
+                        eprintln!("{}: {}", file.display(), e)
+                    });
+                    failed = true;
+                    continue;
+                }
+            };
+
+            let modified = actual != rehashed.stored;
+            // the records of old DB have no metadata to compare
+            let metadata_changed =
+                stored.size.is_some() && !stored.same_metadata(&FileTable::from_path(file));
+            let status = if modified {
+                summary.modified += 1;
+                "MODIFIED"
+            } else if metadata_changed {
+                summary.metadata_changed += 1;
+                "METADATA CHANGED"
+            } else {
+                summary.unchanged += 1;
+                "UNCHANGED"
+            };
+
+            let record = Record {
+                hashes: owned_hash_map(&rehashed.stored),
+                status: Some(status.to_string()),
+                ..Record::for_file(file)
+            };
+            output.emit(record, |_| println!("{}: {}", file.display(), status));
+        }
+
+        for file in FileTable::all(&connection).iter() {
+            if !seen.contains(&(file.root_id, file.full_path.clone())) {
+                summary.missing += 1;
+                let path = resolve_path(&roots, file);
+                let record = Record {
+                    hashes: owned_hash_map(&find_hashes_by_file_id(&connection, file.id.unwrap())),
+                    status: Some("MISSING".to_string()),
+                    ..Record::new(path.to_string_lossy())
+                };
+                output.emit(record, |_| println!("{}: MISSING", path.display()));
+            }
+        }
+
+        if output.is_text() {
+            println!(
+                "{} unchanged, {} modified, {} metadata changed, {} new, {} missing",
+                summary.unchanged,
+                summary.modified,
+                summary.metadata_changed,
+                summary.new,
+                summary.missing
+            );
+        }
+
+        if failed
+            || summary.modified > 0
+            || summary.metadata_changed > 0
+            || summary.new > 0
+            || summary.missing > 0
+        {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rayon::prelude::*;
+
+use crate::{
+    hashes::{
+        algorithm::Algorithm,
+        hashdeep::{HashdeepEntry, HashdeepUtils},
+    },
+    output::{hash_map, Output, OutputOptions, Record},
+    utils::{collect_files, normalize_path},
+};
+
+use super::utils::Mode;
+
+pub struct AuditHashdeepMode {
+    pub known_filepath: PathBuf,
+    pub targets: Vec<PathBuf>,
+    pub output: OutputOptions,
+}
+
+#[derive(Debug, Default)]
+struct AuditSummary {
+    matched: usize,
+    moved: usize,
+    new: usize,
+    changed: usize,
+    missing: usize,
+}
+
+impl Mode for AuditHashdeepMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let known = match Self::read_known(&self.known_filepath) {
+            Ok(k) => k,
+            Err(e) => {
+                eprintln!("{}: {}", self.known_filepath.display(), e);
+                return 255.into();
+            }
+        };
+
+        let file_list = match collect_files(&self.targets) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let actuals: Vec<Result<HashdeepEntry, String>> = file_list
+            .par_iter()
+            .map(|f| HashdeepEntry::from_path(f))
+            .collect();
+
+        let known_by_path: HashMap<String, &HashdeepEntry> = known
+            .iter()
+            .map(|e| (normalize_path(Path::new(&e.filename)), e))
+            .collect();
+        let mut known_by_hash: HashMap<&String, Vec<&HashdeepEntry>> = HashMap::new();
+        for entry in known.iter() {
+            if let Some(h) = entry.strongest_hash() {
+                known_by_hash.entry(h).or_default().push(entry);
+            }
+        }
+
+        let mut summary = AuditSummary::default();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut failed = false;
+        for (file, actual) in file_list.iter().zip(actuals.iter()) {
+            let actual = match actual {
+                Ok(a) => a,
+                Err(e) => {
+                    output.emit(Record::error(file.to_string_lossy(), e), |_| {
+                        eprintln!("{}", e)
+                    });
+                    failed = true;
+                    continue;
+                }
+            };
+
+            let path = normalize_path(Path::new(&actual.filename));
+            let moved_from = actual
+                .strongest_hash()
+                .and_then(|h| known_by_hash.get(h))
+                .and_then(|v| v.iter().find(|k| k.matches(actual)));
+
+            let mut record = Self::record(actual);
+            match known_by_path.get(&path) {
+                Some(k) if k.matches(actual) => {
+                    seen.insert(path.clone());
+                    summary.matched += 1;
+                    record.status = Some("MATCHED".to_string());
+                    output.emit(record, |_| println!("{}: MATCHED", actual.filename));
+                }
+                Some(_) => {
+                    seen.insert(path.clone());
+                    summary.changed += 1;
+                    record.status = Some("CHANGED".to_string());
+                    output.emit(record, |_| println!("{}: CHANGED", actual.filename));
+                }
+                None => match moved_from {
+                    Some(k) => {
+                        seen.insert(normalize_path(Path::new(&k.filename)));
+                        summary.moved += 1;
+                        record.status = Some("MOVED".to_string());
+                        record.previous_path = Some(k.filename.clone());
+                        output.emit(record, |_| {
+                            println!("{}: MOVED from {}", actual.filename, k.filename)
+                        });
+                    }
+                    None => {
+                        summary.new += 1;
+                        record.status = Some("NEW".to_string());
+                        output.emit(record, |_| println!("{}: NEW", actual.filename));
+                    }
+                },
+            }
+        }
+
+        for entry in known.iter() {
+            if !seen.contains(&normalize_path(Path::new(&entry.filename))) {
+                summary.missing += 1;
+                let mut record = Self::record(entry);
+                record.status = Some("MISSING".to_string());
+                output.emit(record, |_| println!("{}: MISSING", entry.filename));
+            }
+        }
+
+        if output.is_text() {
+            println!(
+                "{} matched, {} moved, {} new, {} changed, {} missing",
+                summary.matched, summary.moved, summary.new, summary.changed, summary.missing
+            );
+        }
+
+        if failed
+            || summary.moved > 0
+            || summary.new > 0
+            || summary.changed > 0
+            || summary.missing > 0
+        {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl AuditHashdeepMode {
+    fn record(entry: &HashdeepEntry) -> Record {
+        Record {
+            size: entry.size,
+            hashes: hash_map([
+                (Algorithm::Md5, entry.md5.as_ref()),
+                (Algorithm::Sha1, entry.sha1.as_ref()),
+                (Algorithm::Sha256, entry.sha256.as_ref()),
+            ]),
+            ..Record::new(&entry.filename)
+        }
+    }
+
+    fn read_known(known_filepath: &Path) -> Result<Vec<HashdeepEntry>, String> {
+        let mut file = match File::open(known_filepath) {
+            Ok(f) => f,
+            Err(e) => {
+                return Err(format!("{}", e));
+            }
+        };
+
+        let mut buf = String::new();
+        if let Err(e) = file.read_to_string(&mut buf) {
+            return Err(format!("{}", e));
+        }
+
+        HashdeepUtils::parse(&buf)
+    }
+}
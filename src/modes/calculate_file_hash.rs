@@ -4,58 +4,39 @@ use std::{
 };
 
 use crate::{
-    hashes::{hash::Hash, md5::Md5Hash, sha1::Sha1Hash, sha256::Sha256Hash},
-    print_some,
+    hashes::algorithm::Algorithm,
+    output::{Output, OutputOptions, Record},
 };
 
-use super::utils::Mode;
-
-macro_rules! calc_hash {
-    ($calculator:ty, $path:tt) => {
-        Some(<$calculator>::calc_from_path($path))
-    };
-}
-
-macro_rules! calc_hash_if {
-    ($flag:tt, $calculator:ty, $path:tt) => {
-        if $flag {
-            calc_hash!($calculator, $path)
-        } else {
-            None
-        }
-    };
-}
+use super::utils::{select_algorithms, with_default_template, Mode};
 
 pub struct CalculateFileHashMode {
     pub md5: bool,
     pub sha1: bool,
     pub sha256: bool,
     pub files: Vec<PathBuf>,
+    pub output: OutputOptions,
 }
 
 impl Mode for CalculateFileHashMode {
     fn run(&self) -> ExitCode {
-        let mut hashes = vec![];
+        let algorithms = select_algorithms(self.md5, self.sha1, self.sha256, &self.output);
+        let mut output = Output::new(&with_default_template(&self.output, &algorithms));
+        let mut records = vec![];
         for file in self.files.iter() {
             if !file.exists() {
-                eprintln!("{} does not exist", file.to_string_lossy());
+                let error = format!("{} does not exist", file.to_string_lossy());
+                output.emit(Record::error(file.to_string_lossy(), &error), |_| {
+                    eprintln!("{}", error)
+                });
                 return 1.into();
             }
 
-            let file_hash = (
-                file,
-                Self::calc_hash(file, self.md5, self.sha1, self.sha256),
-            );
-            hashes.push(file_hash);
+            records.push(Self::calc_hash(file, &algorithms));
         }
 
-        for (file, hash) in hashes.iter() {
-            let (md5, sha1, sha256) = hash;
-
-            print_some!(md5);
-            print_some!(sha1);
-            print_some!(sha256);
-            println!("{}", file.to_string_lossy());
+        for record in records.into_iter() {
+            output.emit(record, |_| {});
         }
 
         0.into()
@@ -63,24 +44,13 @@ impl Mode for CalculateFileHashMode {
 }
 
 impl CalculateFileHashMode {
-    fn calc_hash(
-        file: &Path,
-        md5: bool,
-        sha1: bool,
-        sha256: bool,
-    ) -> (Option<String>, Option<String>, Option<String>) {
-        if !md5 && !sha1 && !sha256 {
-            return (
-                calc_hash!(Md5Hash, file),
-                calc_hash!(Sha1Hash, file),
-                calc_hash!(Sha256Hash, file),
-            );
+    fn calc_hash(file: &Path, algorithms: &[Algorithm]) -> Record {
+        Record {
+            hashes: algorithms
+                .iter()
+                .map(|a| (a.name().to_string(), a.calc_from_path(file)))
+                .collect(),
+            ..Record::for_file(file)
         }
-
-        (
-            calc_hash_if!(md5, Md5Hash, file),
-            calc_hash_if!(sha1, Sha1Hash, file),
-            calc_hash_if!(sha256, Sha256Hash, file),
-        )
     }
 }
@@ -1,74 +1,46 @@
 use std::io::{self, Read};
 use std::process::ExitCode;
 
-use super::utils::Mode;
-use crate::hashes::{hash::Hash, md5::Md5Hash, sha1::Sha1Hash, sha256::Sha256Hash};
-use crate::print_some;
-
-macro_rules! calc_hash {
-    ($calculator:ty, $input:tt) => {
-        Some(<$calculator>::calc($input))
-    };
-}
-
-macro_rules! calc_hash_if {
-    ($flag:tt, $calculator:ty, $input:tt) => {
-        if $flag {
-            calc_hash!($calculator, $input)
-        } else {
-            None
-        }
-    };
-}
+use super::utils::{select_algorithms, with_default_template, Mode};
+use crate::hashes::algorithm::Algorithm;
+use crate::output::{Output, OutputOptions, Record};
 
 pub struct CalculateStdinHashMode {
     pub md5: bool,
     pub sha1: bool,
     pub sha256: bool,
+    pub output: OutputOptions,
 }
 
 impl Mode for CalculateStdinHashMode {
     fn run(&self) -> ExitCode {
-        let mut buffer = String::new();
+        let algorithms = select_algorithms(self.md5, self.sha1, self.sha256, &self.output);
+        let mut output = Output::new(&with_default_template(&self.output, &algorithms));
+        let mut buffer = vec![];
         let mut lock = io::stdin().lock();
-        match lock.read_to_string(&mut buffer) {
+        match lock.read_to_end(&mut buffer) {
             Ok(_) => {}
             Err(e) => {
-                eprintln!("{}", e);
+                output.emit(Record::error("-", e.to_string()), |_| eprintln!("{}", e));
                 return 255.into();
             }
         };
 
-        let (md5, sha1, sha256) = Self::calc_hash(&mut buffer, self.md5, self.sha1, self.sha256);
-
-        print_some!(md5);
-        print_some!(sha1);
-        print_some!(sha256);
-        println!("-");
+        output.emit(Self::calc_hash(&buffer, &algorithms), |_| {});
 
         0.into()
     }
 }
 
 impl CalculateStdinHashMode {
-    fn calc_hash(
-        input: &String,
-        md5: bool,
-        sha1: bool,
-        sha256: bool,
-    ) -> (Option<String>, Option<String>, Option<String>) {
-        if !md5 && !sha1 && !sha256 {
-            return (
-                calc_hash!(Md5Hash, input),
-                calc_hash!(Sha1Hash, input),
-                calc_hash!(Sha256Hash, input),
-            );
+    fn calc_hash(input: &[u8], algorithms: &[Algorithm]) -> Record {
+        Record {
+            size: Some(input.len() as u64),
+            hashes: algorithms
+                .iter()
+                .map(|a| (a.name().to_string(), a.calc_bytes(input)))
+                .collect(),
+            ..Record::new("-")
         }
-
-        (
-            calc_hash_if!(md5, Md5Hash, input),
-            calc_hash_if!(sha1, Sha1Hash, input),
-            calc_hash_if!(sha256, Sha256Hash, input),
-        )
     }
 }
@@ -0,0 +1,49 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use crate::{
+    hashes::{
+        algorithm::Algorithm,
+        manifest::{Manifest, ManifestFormat},
+    },
+    ChecksumFileUtils,
+};
+
+use super::utils::Mode;
+
+pub struct ConvertManifestMode {
+    pub checksum_filepaths: Vec<PathBuf>,
+    pub format: ManifestFormat,
+    pub algorithm: Option<Algorithm>,
+    // FROM, TO
+    pub rebase: Option<(PathBuf, PathBuf)>,
+}
+
+impl Mode for ConvertManifestMode {
+    fn run(&self) -> ExitCode {
+        let mut manifest = Manifest::default();
+        for file_path in self.checksum_filepaths.iter() {
+            match ChecksumFileUtils::read_manifest(file_path, self.algorithm) {
+                Ok(m) => manifest.entries.extend(m.entries),
+                Err(e) => {
+                    eprintln!("{}: {}", file_path.display(), e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+
+        if let Some((ref from, ref to)) = self.rebase {
+            manifest.rebase(from, to);
+        }
+
+        match manifest.write(self.format) {
+            Ok(s) => {
+                print!("{}", s);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
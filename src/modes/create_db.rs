@@ -1,23 +1,34 @@
 use rusqlite::Connection;
 
 use crate::{
-    db::{create_database, HASH_TABLE_FILENAME},
-    utils::glob_with_recursive,
-};
-use std::{
-    path::{Path, PathBuf},
-    process::ExitCode,
+    db::{create_database, create_tables, parse_root, scan_roots, store_roots, stored_roots},
+    models::root_table::RootTable,
+    output::{Output, OutputOptions},
 };
+use std::{path::PathBuf, process::ExitCode};
 
 use super::utils::Mode;
 
-pub struct CreateDatabaseMode {}
+pub struct CreateDatabaseMode {
+    pub db_path: PathBuf,
+    // NAME=PATH or PATH
+    pub roots: Vec<String>,
+    pub output: OutputOptions,
+}
 
 impl Mode for CreateDatabaseMode {
     fn run(&self) -> ExitCode {
-        let db_path = Path::new(HASH_TABLE_FILENAME);
+        let db_path = self.db_path.as_path();
+        let roots: Vec<RootTable> = match self.roots.iter().map(|r| parse_root(r)).collect() {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
         if db_path.exists() {
-            match std::fs::remove_file(&db_path) {
+            match std::fs::remove_file(db_path) {
                 Ok(_) => {}
                 Err(e) => {
                     eprintln!("{}", e);
@@ -26,13 +37,21 @@ impl Mode for CreateDatabaseMode {
             };
         }
 
-        let mut file_list: Vec<PathBuf> = vec![];
-        glob_with_recursive("./*", &mut |p| {
-            file_list.push(p.clone());
-        });
+        let mut connection = Connection::open(db_path).unwrap();
+        create_tables(&mut connection);
+        if let Err(e) = store_roots(&connection, &roots) {
+            eprintln!("{}", e);
+            return 255.into();
+        }
 
-        let mut connection = Connection::open(&db_path).unwrap();
-        create_database(&mut connection, &file_list);
+        let file_list = match scan_roots(&stored_roots(&connection), db_path) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+        create_database(&mut connection, &file_list, &mut Output::new(&self.output));
 
         0.into()
     }
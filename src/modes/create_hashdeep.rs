@@ -0,0 +1,55 @@
+use std::{env, path::PathBuf, process::ExitCode};
+
+use rayon::prelude::*;
+
+use crate::{
+    hashes::hashdeep::{HashdeepEntry, HashdeepUtils},
+    utils::collect_files,
+};
+
+use super::utils::Mode;
+
+pub struct CreateHashdeepMode {
+    pub files: Vec<PathBuf>,
+}
+
+impl Mode for CreateHashdeepMode {
+    fn run(&self) -> ExitCode {
+        let file_list = match collect_files(&self.files) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1.into();
+            }
+        };
+
+        let entries: Vec<Result<HashdeepEntry, String>> = file_list
+            .par_iter()
+            .map(|f| HashdeepEntry::from_path(f))
+            .collect();
+
+        let invoked_from = env::current_dir().unwrap_or_default();
+        let command_line = env::args().collect::<Vec<String>>().join(" ");
+        println!(
+            "{}",
+            HashdeepUtils::format_header(&invoked_from, &command_line)
+        );
+
+        let mut failed = false;
+        for entry in entries.iter() {
+            match entry {
+                Ok(e) => println!("{}", HashdeepUtils::format_line(e)),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            1.into()
+        } else {
+            0.into()
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use crate::hashes::{
+    algorithm::Algorithm,
+    manifest::{Manifest, ManifestEntry, ManifestFormat},
+};
+
+use super::utils::Mode;
+
+pub struct CreateSfvMode {
+    pub files: Vec<PathBuf>,
+}
+
+impl Mode for CreateSfvMode {
+    fn run(&self) -> ExitCode {
+        let mut manifest = Manifest::default();
+        for file in self.files.iter() {
+            if !file.exists() {
+                eprintln!("{} does not exist", file.to_string_lossy());
+                return 1.into();
+            }
+
+            let hash = Algorithm::Crc32.calc_from_path(file);
+            manifest.entries.push(ManifestEntry {
+                path: file.to_string_lossy().to_string(),
+                size: None,
+                hashes: [(Algorithm::Crc32, hash)].into(),
+            });
+        }
+
+        match manifest.write(ManifestFormat::Sfv) {
+            Ok(s) => print!("{}", s),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1.into();
+            }
+        }
+
+        0.into()
+    }
+}
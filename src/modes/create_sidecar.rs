@@ -0,0 +1,44 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use crate::{hashes::algorithm::Algorithm, utils::collect_files, ChecksumFileUtils};
+
+use super::utils::Mode;
+
+pub struct CreateSidecarMode {
+    pub algorithms: Vec<Algorithm>,
+    pub files: Vec<PathBuf>,
+}
+
+impl Mode for CreateSidecarMode {
+    fn run(&self) -> ExitCode {
+        let file_list = match collect_files(&self.files) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1.into();
+            }
+        };
+
+        for file in file_list.iter() {
+            // do not create sidecar files of sidecar files
+            if ChecksumFileUtils::sidecar_algorithm(file).is_some() {
+                continue;
+            }
+
+            let filename = file.file_name().unwrap().to_string_lossy();
+            for algorithm in self.algorithms.iter() {
+                let hash = algorithm.calc_from_path(file);
+                let sidecar = ChecksumFileUtils::sidecar_path(file, *algorithm);
+                let content = format!("{}\n", ChecksumFileUtils::format_sidecar(&hash, &filename));
+                if let Err(e) = fs::write(&sidecar, content) {
+                    eprintln!("{}: {}", sidecar.display(), e);
+                    return 1.into();
+                }
+
+                println!("{}", sidecar.display());
+            }
+        }
+
+        0.into()
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, Write},
+    path::{self, Path, PathBuf},
+    process::ExitCode,
+    time::UNIX_EPOCH,
+};
+
+use clap::ValueEnum;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{find_duplicate_groups, resolve_path, stored_roots},
+    hashes::algorithm::Algorithm,
+    models::{file_table::FileTable, model::Model},
+    output::{hash_map, Output, OutputOptions, Record},
+    utils::{is_same_file, mode_of, reflink, same_contents, set_mode},
+};
+
+use super::utils::Mode;
+
+pub static UNDO_LOG_FILENAME: &str = "dedupe_undo.log";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkType {
+    #[default]
+    Hard,
+    Reflink,
+}
+
+// a line of the undo log, enough to give the replaced file its own copy again
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub link: LinkType,
+    pub kept: String,
+    pub replaced: String,
+    pub mode: Option<u32>,
+    // nanoseconds since the UNIX epoch
+    pub mtime: Option<i64>,
+}
+
+#[derive(Default)]
+struct Summary {
+    deduped: usize,
+    already_linked: usize,
+    modified: usize,
+    missing: usize,
+    errors: usize,
+    saved: u64,
+}
+
+pub struct DedupeDatabaseMode {
+    pub db_path: PathBuf,
+    pub link: LinkType,
+    pub dry_run: bool,
+    pub undo_log: PathBuf,
+    pub output: OutputOptions,
+}
+
+impl Mode for DedupeDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let mut connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let mut undo_log = if self.dry_run {
+            None
+        } else {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.undo_log)
+            {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    eprintln!("{}: {}", self.undo_log.display(), e);
+                    return 255.into();
+                }
+            }
+        };
+
+        let roots = stored_roots(&connection);
+        let mut summary = Summary::default();
+        // the records whose inode or ctime are changed by linking
+        let mut changed: Vec<(FileTable, PathBuf)> = vec![];
+        for group in find_duplicate_groups(&connection, true) {
+            let mut kept: Option<(FileTable, PathBuf, Metadata)> = None;
+            for file in group.files.into_iter() {
+                let path = resolve_path(&roots, &file);
+                let record = Record {
+                    hashes: hash_map([(Algorithm::Sha256, Some(&group.hash))]),
+                    ..Record::for_file(&path)
+                };
+                let metadata = match path.metadata() {
+                    Ok(m) => m,
+                    Err(_) => {
+                        summary.missing += 1;
+                        let record = Record {
+                            status: Some("MISSING".to_string()),
+                            ..record
+                        };
+                        output.emit(record, |_| println!("{}: MISSING", path.display()));
+                        continue;
+                    }
+                };
+
+                // the first file found in the group is kept as it is
+                let (kept_file, kept_path, kept_metadata) = match kept {
+                    Some(ref k) => k,
+                    None => {
+                        kept = Some((file, path, metadata));
+                        continue;
+                    }
+                };
+
+                let status =
+                    match self.dedupe(kept_path, kept_metadata, &path, &metadata, &mut undo_log) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            summary.errors += 1;
+                            output.emit(Record::error(path.to_string_lossy(), &e), |_| {
+                                eprintln!("{}: {}", path.display(), e)
+                            });
+                            continue;
+                        }
+                    };
+                match status {
+                    "ALREADY LINKED" => summary.already_linked += 1,
+                    "MODIFIED" => summary.modified += 1,
+                    _ => {
+                        summary.deduped += 1;
+                        summary.saved += metadata.len();
+                        if !self.dry_run {
+                            if !changed.iter().any(|(f, _)| f.id == kept_file.id) {
+                                changed.push((kept_file.clone(), kept_path.clone()));
+                            }
+                            changed.push((file, path.clone()));
+                        }
+                    }
+                }
+
+                let record = Record {
+                    status: Some(status.to_string()),
+                    ..record
+                };
+                output.emit(record, |_| match status {
+                    "MODIFIED" => println!("{}: {}", path.display(), status),
+                    _ => println!("{}: {} -> {}", path.display(), status, kept_path.display()),
+                });
+            }
+        }
+
+        // the hashes are the same, only the metadata is refreshed
+        let tx = connection.transaction().unwrap();
+        for (file, path) in changed.into_iter() {
+            let current = FileTable::from_path(&path);
+            FileTable {
+                size: current.size,
+                mtime: current.mtime,
+                ctime: current.ctime,
+                inode: current.inode,
+                ..file
+            }
+            .update(&tx);
+        }
+        if let Err(e) = tx.commit() {
+            eprintln!("transaction commit failed. {:?}", e);
+            return 255.into();
+        }
+
+        if output.is_text() {
+            println!(
+                "{} deduplicated, {} already linked, {} modified, {} missing, {} errors, {} bytes {}",
+                summary.deduped,
+                summary.already_linked,
+                summary.modified,
+                summary.missing,
+                summary.errors,
+                summary.saved,
+                if self.dry_run { "to save" } else { "saved" }
+            );
+        }
+
+        if summary.modified + summary.missing + summary.errors > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl DedupeDatabaseMode {
+    // the files are compared byte by byte, since DB may be older than them
+    fn dedupe(
+        &self,
+        kept: &Path,
+        kept_metadata: &Metadata,
+        path: &Path,
+        metadata: &Metadata,
+        undo_log: &mut Option<File>,
+    ) -> Result<&'static str, String> {
+        if is_same_file(kept_metadata, metadata) {
+            return Ok("ALREADY LINKED");
+        }
+        if !same_contents(kept, path).map_err(|e| e.to_string())? {
+            return Ok("MODIFIED");
+        }
+        if self.dry_run {
+            return Ok(match self.link {
+                LinkType::Hard => "TO LINK",
+                LinkType::Reflink => "TO CLONE",
+            });
+        }
+
+        self.replace(kept, path, metadata)
+            .map_err(|e| e.to_string())?;
+
+        let entry = UndoEntry {
+            link: self.link,
+            kept: absolute_string(kept),
+            replaced: absolute_string(path),
+            mode: mode_of(metadata),
+            mtime: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos() as i64),
+        };
+        if let Some(log) = undo_log {
+            let line = serde_json::to_string(&entry).unwrap();
+            writeln!(log, "{}", line)
+                .and_then(|_| log.flush())
+                .map_err(|e| format!("{}: {}", self.undo_log.display(), e))?;
+        }
+
+        Ok(match self.link {
+            LinkType::Hard => "LINKED",
+            LinkType::Reflink => "CLONED",
+        })
+    }
+
+    // the link is made next to the file and renamed over it, so the file is never lost
+    fn replace(&self, kept: &Path, path: &Path, metadata: &Metadata) -> io::Result<()> {
+        let temporary = temporary_path(path);
+        let result = (|| {
+            match self.link {
+                LinkType::Hard => fs::hard_link(kept, &temporary)?,
+                LinkType::Reflink => {
+                    let source = File::open(kept)?;
+                    let dest = File::create_new(&temporary)?;
+                    reflink(&source, &dest)?;
+                    if let Some(mode) = mode_of(metadata) {
+                        set_mode(&dest, mode)?;
+                    }
+                    dest.set_modified(metadata.modified()?)?;
+                }
+            }
+            fs::rename(&temporary, path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        result
+    }
+}
+
+// a hidden file in the same directory, so it can be renamed over the file
+pub fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.rhh-dedupe", name))
+}
+
+fn absolute_string(path: &Path) -> String {
+    path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
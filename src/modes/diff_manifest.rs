@@ -0,0 +1,150 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use crate::{
+    hashes::{
+        algorithm::Algorithm,
+        manifest::{Manifest, ManifestEntry},
+    },
+    output::{Output, OutputOptions},
+    utils::normalize_path,
+    ChecksumFileUtils,
+};
+
+use super::utils::Mode;
+
+pub struct DiffManifestMode {
+    pub old_filepath: PathBuf,
+    pub new_filepath: PathBuf,
+    pub algorithm: Option<Algorithm>,
+    pub output: OutputOptions,
+}
+
+#[derive(Debug, Default)]
+struct DiffSummary {
+    added: usize,
+    removed: usize,
+    changed: usize,
+    renamed: usize,
+}
+
+impl Mode for DiffManifestMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let old = match Self::read(&self.old_filepath, self.algorithm) {
+            Ok(m) => m,
+            Err(e) => return e,
+        };
+        let new = match Self::read(&self.new_filepath, self.algorithm) {
+            Ok(m) => m,
+            Err(e) => return e,
+        };
+
+        let old_by_path: HashMap<String, &ManifestEntry> = old
+            .entries
+            .iter()
+            .map(|e| (normalize_path(Path::new(&e.path)), e))
+            .collect();
+        let new_paths: HashSet<String> = new
+            .entries
+            .iter()
+            .map(|e| normalize_path(Path::new(&e.path)))
+            .collect();
+
+        // the removed entries are the candidates of the renamed ones
+        let mut removed_by_hash: HashMap<(Algorithm, &String), Vec<&ManifestEntry>> =
+            HashMap::new();
+        for entry in old.entries.iter() {
+            if new_paths.contains(&normalize_path(Path::new(&entry.path))) {
+                continue;
+            }
+            for (algorithm, hash) in entry.hashes.iter() {
+                removed_by_hash
+                    .entry((*algorithm, hash))
+                    .or_default()
+                    .push(entry);
+            }
+        }
+
+        let mut summary = DiffSummary::default();
+        let mut renamed: HashSet<String> = HashSet::new();
+        for entry in new.entries.iter() {
+            let path = normalize_path(Path::new(&entry.path));
+            let mut record = entry.to_record();
+            match old_by_path.get(&path) {
+                Some(o) if o.matches(entry) => {}
+                Some(_) => {
+                    summary.changed += 1;
+                    record.status = Some("CHANGED".to_string());
+                    output.emit(record, |_| println!("{}: CHANGED", entry.path));
+                }
+                None => {
+                    let renamed_from = entry
+                        .hashes
+                        .iter()
+                        .rev()
+                        .filter_map(|(a, h)| removed_by_hash.get(&(*a, h)))
+                        .flatten()
+                        .find(|o| {
+                            !renamed.contains(&normalize_path(Path::new(&o.path)))
+                                && o.matches(entry)
+                        });
+
+                    match renamed_from {
+                        Some(o) => {
+                            renamed.insert(normalize_path(Path::new(&o.path)));
+                            summary.renamed += 1;
+                            record.status = Some("RENAMED".to_string());
+                            record.previous_path = Some(o.path.clone());
+                            output.emit(record, |_| {
+                                println!("{}: RENAMED from {}", entry.path, o.path)
+                            });
+                        }
+                        None => {
+                            summary.added += 1;
+                            record.status = Some("ADDED".to_string());
+                            output.emit(record, |_| println!("{}: ADDED", entry.path));
+                        }
+                    }
+                }
+            }
+        }
+
+        for entry in old.entries.iter() {
+            let path = normalize_path(Path::new(&entry.path));
+            if new_paths.contains(&path) || renamed.contains(&path) {
+                continue;
+            }
+
+            summary.removed += 1;
+            let mut record = entry.to_record();
+            record.status = Some("REMOVED".to_string());
+            output.emit(record, |_| println!("{}: REMOVED", entry.path));
+        }
+
+        if output.is_text() {
+            println!(
+                "{} added, {} removed, {} changed, {} renamed",
+                summary.added, summary.removed, summary.changed, summary.renamed
+            );
+        }
+
+        if summary.added > 0 || summary.removed > 0 || summary.changed > 0 || summary.renamed > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl DiffManifestMode {
+    fn read(file_path: &Path, algorithm: Option<Algorithm>) -> Result<Manifest, ExitCode> {
+        ChecksumFileUtils::read_manifest(file_path, algorithm).map_err(|e| {
+            eprintln!("{}: {}", file_path.display(), e);
+            ExitCode::from(255)
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::{
+    db::{find_duplicate_groups, resolve_path, stored_roots},
+    hashes::algorithm::Algorithm,
+    output::{hash_map, Output, OutputOptions, Record},
+};
+
+use super::utils::Mode;
+
+pub struct DuplicatesDatabaseMode {
+    pub db_path: PathBuf,
+    pub same_size: bool,
+    pub output: OutputOptions,
+}
+
+impl Mode for DuplicatesDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let connection =
+            match Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 255.into();
+                }
+            };
+
+        let roots = stored_roots(&connection);
+        let groups = find_duplicate_groups(&connection, self.same_size);
+        for (i, group) in groups.iter().enumerate() {
+            if output.is_text() {
+                println!(
+                    "{}  {} files, {} bytes wasted",
+                    group.hash,
+                    group.files.len(),
+                    group.wasted()
+                );
+            }
+
+            for file in group.files.iter() {
+                let path = resolve_path(&roots, file);
+                let record = Record {
+                    size: Some(group.size),
+                    hashes: hash_map([(Algorithm::Sha256, Some(&group.hash))]),
+                    group: Some(i + 1),
+                    ..Record::for_file(&path)
+                };
+                output.emit(record, |_| println!("  {}", path.display()));
+            }
+        }
+
+        if output.is_text() {
+            println!(
+                "{} group(s), {} duplicate file(s), {} bytes wasted",
+                groups.len(),
+                groups.iter().map(|g| g.files.len() - 1).sum::<usize>(),
+                groups.iter().map(|g| g.wasted()).sum::<u64>()
+            );
+        }
+
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,39 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use crate::hashes::{algorithm::Algorithm, hash::CheckSummary};
+use crate::output::{Output, OutputOptions, Record};
+use crate::ChecksumFileUtils;
+
+use super::utils::Mode;
+
+pub struct ExpectDigestMode {
+    pub algorithm: Algorithm,
+    pub hash: String,
+    pub files: Vec<PathBuf>,
+    pub output: OutputOptions,
+}
+
+impl Mode for ExpectDigestMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let mut summary = CheckSummary::default();
+        let mut errors = 0;
+        for file in self.files.iter() {
+            match ChecksumFileUtils::check_digest(file, self.algorithm, &self.hash, &mut output) {
+                Ok(s) => summary.merge(&s),
+                Err(e) => {
+                    output.emit(Record::error(file.to_string_lossy(), &e), |_| {
+                        eprintln!("{}: {}", file.display(), e)
+                    });
+                    errors += 1;
+                }
+            }
+        }
+
+        if summary.failed > 0 || errors > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::{collections::HashMap, path::PathBuf, process::ExitCode};
+
+use rusqlite::Connection;
+
+use crate::{
+    db::{resolve_path, stored_roots},
+    hashes::algorithm::Algorithm,
+    models::{
+        file_table::FileTable, md5_hash_table::Md5HashTable, model::Model,
+        sha1_hash_table::Sha1HashTable, sha256_hash_table::Sha256HashTable,
+    },
+    output::{hash_map, Output, OutputOptions, Record},
+};
+
+use super::utils::{with_default_template, Mode};
+
+pub struct ExportDatabaseMode {
+    pub db_path: PathBuf,
+    pub output: OutputOptions,
+}
+
+impl Mode for ExportDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 1.into();
+        }
+
+        let connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1.into();
+            }
+        };
+
+        // file_id -> hash
+        let md5: HashMap<i64, String> = Md5HashTable::all(&connection)
+            .iter()
+            .map(|h| (h.file_id, h.hash.clone()))
+            .collect();
+        let sha1: HashMap<i64, String> = Sha1HashTable::all(&connection)
+            .iter()
+            .map(|h| (h.file_id, h.hash.clone()))
+            .collect();
+        let sha256: HashMap<i64, String> = Sha256HashTable::all(&connection)
+            .iter()
+            .map(|h| (h.file_id, h.hash.clone()))
+            .collect();
+
+        let roots = stored_roots(&connection);
+        let algorithms = [Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256];
+        let mut output = Output::new(&with_default_template(&self.output, &algorithms));
+        for file in FileTable::all(&connection).iter() {
+            let id = file.id.unwrap();
+            let (md5, sha1, sha256) = (md5.get(&id), sha1.get(&id), sha256.get(&id));
+
+            let record = Record {
+                hashes: hash_map([
+                    (Algorithm::Md5, md5),
+                    (Algorithm::Sha1, sha1),
+                    (Algorithm::Sha256, sha256),
+                ]),
+                // the size and mtime stored in DB, which may differ from the filesystem
+                size: file.size.map(|s| s as u64),
+                mtime: file.mtime.map(|m| (m / 1_000_000_000) as u64),
+                ..Record::new(resolve_path(&roots, file).to_string_lossy())
+            };
+            output.emit(record, |_| {});
+        }
+
+        0.into()
+    }
+}
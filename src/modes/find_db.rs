@@ -0,0 +1,132 @@
+use std::{
+    io::{self, Read},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    db::{find_file_ids_by_hash, resolve_path, stored_roots, HASH_TABLES},
+    hashes::algorithm::Algorithm,
+    models::{file_table::FileTable, model::Model},
+    output::{hash_map, Output, OutputOptions, Record},
+    ChecksumFileUtils,
+};
+
+use super::utils::Mode;
+
+pub struct FindDatabaseMode {
+    pub db_path: PathBuf,
+    // None reads the digests from stdin
+    pub digests: Option<Vec<String>>,
+    pub output: OutputOptions,
+}
+
+impl Mode for FindDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let roots = stored_roots(&connection);
+        let digests = match self.digests {
+            Some(ref d) => d.clone(),
+            None => match Self::read_digests() {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 255.into();
+                }
+            },
+        };
+
+        let mut not_found = 0;
+        for digest in digests.iter() {
+            let (algorithm, hash) = match Self::parse(digest) {
+                Ok(d) => d,
+                Err(e) => {
+                    not_found += 1;
+                    output.emit(Record::error(digest, &e), |_| eprintln!("{}", e));
+                    continue;
+                }
+            };
+
+            let table_name = HASH_TABLES.iter().find(|(a, _)| *a == algorithm).unwrap().1;
+            let paths: Vec<PathBuf> = find_file_ids_by_hash(&connection, table_name, &hash)
+                .into_iter()
+                .map(|id| resolve_path(&roots, &FileTable::get(&connection, id)))
+                .collect();
+            if paths.is_empty() {
+                not_found += 1;
+                let record = Record {
+                    hashes: hash_map([(algorithm, Some(&hash))]),
+                    status: Some("NOT FOUND".to_string()),
+                    ..Record::new("")
+                };
+                output.emit(record, |_| println!("{}: NOT FOUND", digest));
+                continue;
+            }
+
+            for path in paths.iter() {
+                let record = Record {
+                    hashes: hash_map([(algorithm, Some(&hash))]),
+                    status: Some("FOUND".to_string()),
+                    ..Record::for_file(path)
+                };
+                output.emit(record, |_| println!("{}  {}", hash, path.display()));
+            }
+        }
+
+        if not_found > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl FindDatabaseMode {
+    // the algorithms in DB have different lengths
+    fn parse(digest: &str) -> Result<(Algorithm, String), String> {
+        let hint = HASH_TABLES
+            .iter()
+            .map(|(a, _)| *a)
+            .find(|a| a.get_hash_length() == digest.len());
+        let (algorithm, hash) = ChecksumFileUtils::parse_digest(digest, hint)?;
+        if HASH_TABLES.iter().any(|(a, _)| *a == algorithm) {
+            Ok((algorithm, hash))
+        } else {
+            Err(format!(
+                "{} is not stored in DB: {}",
+                algorithm.name(),
+                digest
+            ))
+        }
+    }
+
+    // one digest per line, followed by anything such as a filename
+    fn read_digests() -> Result<Vec<String>, String> {
+        let mut buf = String::new();
+        if let Err(e) = io::stdin().lock().read_to_string(&mut buf) {
+            return Err(format!("{}", e));
+        }
+
+        Ok(buf
+            .lines()
+            .filter_map(|l| l.split_whitespace().next())
+            .map(|d| d.to_string())
+            .collect())
+    }
+}
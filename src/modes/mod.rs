@@ -1,8 +1,25 @@
+pub mod audit_db;
+pub mod audit_hashdeep;
 pub mod calculate_file_hash;
 pub mod calculate_stdin_hash;
+pub mod convert;
 pub mod create_db;
+pub mod create_hashdeep;
+pub mod create_sfv;
+pub mod create_sidecar;
+pub mod dedupe_db;
+pub mod diff_manifest;
+pub mod duplicates_db;
+pub mod expect_digest;
+pub mod export_db;
+pub mod find_db;
+pub mod rebind_root;
+pub mod scrub_db;
+pub mod sign_manifest;
+pub mod undo_dedupe;
 pub mod unexpected_arguments;
 pub mod update_db;
 pub mod use_db;
 pub mod utils;
 pub mod validate_checksum;
+pub mod validate_sidecar;
@@ -0,0 +1,77 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use rusqlite::Connection;
+
+use crate::{
+    db::{create_tables, parse_root},
+    models::{model::Model, root_table::RootTable},
+};
+
+use super::utils::Mode;
+
+pub struct RebindRootMode {
+    pub db_path: PathBuf,
+    // NAME=PATH
+    pub root: String,
+}
+
+impl Mode for RebindRootMode {
+    fn run(&self) -> ExitCode {
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let mut connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+        create_tables(&mut connection);
+
+        if !self.root.contains('=') {
+            eprintln!("invalid option: --rebind requires NAME=PATH");
+            return 255.into();
+        }
+        let new_root = match parse_root(&self.root) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let roots = RootTable::all(&connection);
+        let root = match roots.iter().find(|r| r.name == new_root.name) {
+            Some(r) => r,
+            None => {
+                let names: Vec<&str> = roots.iter().map(|r| r.name.as_str()).collect();
+                eprintln!(
+                    "unknown root: {} (available: {})",
+                    new_root.name,
+                    names.join(", ")
+                );
+                return 255.into();
+            }
+        };
+        if let Some(r) = roots
+            .iter()
+            .find(|r| r.path == new_root.path && r.id != root.id)
+        {
+            eprintln!("{} is already bound to {}", r.path, r.name);
+            return 255.into();
+        }
+
+        RootTable {
+            path: new_root.path.clone(),
+            ..(**root).clone()
+        }
+        .update(&connection);
+        println!("{}: {} -> {}", root.name, root.path, new_root.path);
+
+        0.into()
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    db::{create_tables, rehash_stored, resolve_path, stored_roots, unix_time, RehashedFile},
+    models::{file_table::FileTable, model::Model},
+    output::{owned_hash_map, Output, OutputOptions, Record},
+};
+
+use super::utils::Mode;
+
+pub struct ScrubDatabaseMode {
+    pub db_path: PathBuf,
+    // a random N% of the files
+    pub sample: Option<u8>,
+    // the N% of the files verified least recently
+    pub oldest: Option<u8>,
+    pub output: OutputOptions,
+}
+
+#[derive(Debug, Default)]
+struct ScrubSummary {
+    ok: usize,
+    corrupted: usize,
+    modified: usize,
+    missing: usize,
+}
+
+impl Mode for ScrubDatabaseMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let mut connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+        // add the columns to DB created by older versions
+        create_tables(&mut connection);
+
+        let files: Vec<FileTable> = match (self.sample, self.oldest) {
+            (Some(p), _) => FileTable::sample(&connection, p),
+            (_, Some(p)) => FileTable::least_recently_verified(&connection, p),
+            _ => FileTable::all(&connection)
+                .iter()
+                .map(|f| (**f).clone())
+                .collect(),
+        };
+        let roots = stored_roots(&connection);
+        let files = files
+            .into_iter()
+            .map(|f| {
+                let path = resolve_path(&roots, &f);
+                (f, path)
+            })
+            .collect();
+
+        let mut summary = ScrubSummary::default();
+        let mut errors = 0;
+        let mut verified = vec![];
+        for RehashedFile {
+            file,
+            path,
+            stored,
+            actual,
+        } in rehash_stored(&connection, files)
+        {
+            let path = path.as_path();
+            let mut record = Record {
+                hashes: owned_hash_map(&stored),
+                ..Record::for_file(path)
+            };
+
+            if !path.exists() {
+                summary.missing += 1;
+                record.status = Some("MISSING".to_string());
+                output.emit(record, |_| println!("{}: MISSING", path.display()));
+                continue;
+            }
+            let actual = match actual {
+                Ok(a) => a,
+                Err(e) => {
+                    errors += 1;
+                    output.emit(Record::error(path.to_string_lossy(), &e), |_| {
+                        eprintln!("{}: {}", path.display(), e)
+                    });
+                    continue;
+                }
+            };
+
+            let status = if actual == stored {
+                summary.ok += 1;
+                "OK"
+            } else if Self::is_corrupted(&file, path) {
+                summary.corrupted += 1;
+                "CORRUPTED"
+            } else {
+                summary.modified += 1;
+                "MODIFIED"
+            };
+            record.status = Some(status.to_string());
+            output.emit(record, |_| println!("{}: {}", path.display(), status));
+
+            if status == "OK" {
+                verified.push(file);
+            }
+        }
+
+        let now = unix_time();
+        let tx = connection.transaction().unwrap();
+        for file in verified.into_iter() {
+            FileTable {
+                verified: Some(now),
+                ..file
+            }
+            .update(&tx);
+        }
+        if let Err(e) = tx.commit() {
+            eprintln!("transaction commit failed. {:?}", e);
+        }
+
+        if output.is_text() {
+            println!(
+                "{} ok, {} corrupted, {} modified, {} missing",
+                summary.ok, summary.corrupted, summary.modified, summary.missing
+            );
+        }
+
+        if errors > 0 || summary.corrupted > 0 || summary.modified > 0 || summary.missing > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl ScrubDatabaseMode {
+    // an edit changes the size or mtime, but the media corruption does not
+    fn is_corrupted(stored: &FileTable, path: &Path) -> bool {
+        let actual = FileTable::from_path(path);
+        stored.size.is_some()
+            && stored.mtime.is_some()
+            && (stored.size, stored.mtime) == (actual.size, actual.mtime)
+    }
+}
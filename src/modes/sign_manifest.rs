@@ -0,0 +1,63 @@
+use std::{
+    fs,
+    io::{self, BufRead, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use crate::signature::SecretKey;
+
+use super::utils::Mode;
+
+pub struct SignManifestMode {
+    pub secret_key_filepath: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+impl Mode for SignManifestMode {
+    fn run(&self) -> ExitCode {
+        let secret_key = match SecretKey::read(&self.secret_key_filepath, Self::read_passphrase) {
+            Ok(k) => k,
+            Err(e) => {
+                eprintln!("{}: {}", self.secret_key_filepath.display(), e);
+                return 1.into();
+            }
+        };
+
+        for file in self.files.iter() {
+            let content = match fs::read(file) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("{}: {}", file.display(), e);
+                    return 1.into();
+                }
+            };
+
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+            let signature_path = secret_key.format().signature_path(file);
+            if let Err(e) = fs::write(&signature_path, secret_key.sign(&content, &file_name)) {
+                eprintln!("{}: {}", signature_path.display(), e);
+                return 1.into();
+            }
+
+            println!("{}", signature_path.display());
+        }
+
+        0.into()
+    }
+}
+
+impl SignManifestMode {
+    // the passphrase can also be piped for scripts
+    fn read_passphrase() -> Result<String, String> {
+        if io::stdin().is_terminal() {
+            return rpassword::prompt_password("Password: ").map_err(|e| format!("{}", e));
+        }
+
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+            Err(e) => Err(format!("{}", e)),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{
+    output::{Output, OutputOptions, Record},
+    utils::{copy_contents, set_mode},
+};
+
+use super::{
+    dedupe_db::{temporary_path, UndoEntry},
+    utils::Mode,
+};
+
+pub struct UndoDedupeMode {
+    pub undo_log: PathBuf,
+    pub output: OutputOptions,
+}
+
+impl Mode for UndoDedupeMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let entries = match self.read_entries() {
+            Ok(e) => e,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let (mut restored, mut missing, mut errors) = (0, 0, 0);
+        // the last replaced file is restored first
+        for entry in entries.iter().rev() {
+            let path = Path::new(&entry.replaced);
+            if !path.exists() {
+                missing += 1;
+                let record = Record {
+                    status: Some("MISSING".to_string()),
+                    ..Record::new(path.to_string_lossy())
+                };
+                output.emit(record, |_| println!("{}: MISSING", path.display()));
+                continue;
+            }
+
+            if let Err(e) = Self::restore(path, entry) {
+                errors += 1;
+                output.emit(Record::error(path.to_string_lossy(), e.to_string()), |_| {
+                    eprintln!("{}: {}", path.display(), e)
+                });
+                continue;
+            }
+
+            restored += 1;
+            let record = Record {
+                status: Some("RESTORED".to_string()),
+                ..Record::for_file(path)
+            };
+            output.emit(record, |_| println!("{}: RESTORED", path.display()));
+        }
+
+        if output.is_text() {
+            println!(
+                "{} restored, {} missing, {} errors",
+                restored, missing, errors
+            );
+        }
+
+        if missing + errors > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl UndoDedupeMode {
+    fn read_entries(&self) -> Result<Vec<UndoEntry>, String> {
+        let log = fs::read_to_string(&self.undo_log)
+            .map_err(|e| format!("{}: {}", self.undo_log.display(), e))?;
+
+        log.lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(i, l)| {
+                serde_json::from_str(l)
+                    .map_err(|e| format!("{}:{}: {}", self.undo_log.display(), i + 1, e))
+            })
+            .collect()
+    }
+
+    // a new copy of the bytes takes the place of the link, with the original permissions and mtime
+    fn restore(path: &Path, entry: &UndoEntry) -> io::Result<()> {
+        let temporary = temporary_path(path);
+        let result = (|| {
+            let mut dest = File::create_new(&temporary)?;
+            copy_contents(path, &mut dest)?;
+            if let Some(mode) = entry.mode {
+                set_mode(&dest, mode)?;
+            }
+            if let Some(mtime) = entry.mtime {
+                dest.set_modified(UNIX_EPOCH + Duration::from_nanos(mtime as u64))?;
+            }
+            fs::rename(&temporary, path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+        result
+    }
+}
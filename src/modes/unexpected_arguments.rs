@@ -1,12 +1,8 @@
 use std::process::ExitCode;
 
-use crate::Args;
-
 use super::utils::Mode;
 
-pub struct UnexpectedArgumentsMode {
-    pub args: Args,
-}
+pub struct UnexpectedArgumentsMode;
 
 impl Mode for UnexpectedArgumentsMode {
     fn run(&self) -> ExitCode {
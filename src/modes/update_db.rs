@@ -1,30 +1,61 @@
-use std::{
-    path::{Path, PathBuf},
-    process::ExitCode,
-};
+use std::{path::PathBuf, process::ExitCode};
 
 use rusqlite::Connection;
 
 use crate::{
-    db::{create_database, HASH_TABLE_FILENAME},
-    utils::glob_with_recursive,
+    db::{
+        adopt_legacy_files, create_tables, parse_root, prune_database, scan_roots, store_roots,
+        stored_roots, update_database,
+    },
+    models::root_table::RootTable,
+    output::{Output, OutputOptions},
 };
 
 use super::utils::Mode;
 
-pub struct UpdateDatabaseMode {}
+pub struct UpdateDatabaseMode {
+    pub db_path: PathBuf,
+    // NAME=PATH or PATH, added to the roots stored in DB
+    pub roots: Vec<String>,
+    pub paranoid: bool,
+    pub prune: bool,
+    pub output: OutputOptions,
+}
 
 impl Mode for UpdateDatabaseMode {
     fn run(&self) -> ExitCode {
-        let db_path = Path::new(HASH_TABLE_FILENAME);
-
-        let mut file_list: Vec<PathBuf> = vec![];
-        glob_with_recursive("./*", &mut |p| {
-            file_list.push(p.clone());
-        });
-
-        let mut connection = Connection::open(&db_path).unwrap();
-        create_database(&mut connection, &file_list);
+        let db_path = self.db_path.as_path();
+        let roots: Vec<RootTable> = match self.roots.iter().map(|r| parse_root(r)).collect() {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let mut connection = Connection::open(db_path).unwrap();
+        create_tables(&mut connection);
+        adopt_legacy_files(&connection);
+        if let Err(e) = store_roots(&connection, &roots) {
+            eprintln!("{}", e);
+            return 255.into();
+        }
+
+        let file_list = match scan_roots(&stored_roots(&connection), db_path) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let mut output = Output::new(&self.output);
+        update_database(&mut connection, &file_list, self.paranoid, &mut output);
+
+        if self.prune {
+            let pruned = prune_database(&mut connection, &file_list, &mut output);
+            eprintln!("{} file(s) pruned", pruned);
+        }
 
         0.into()
     }
@@ -1,15 +1,126 @@
-use std::process::ExitCode;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use crate::Args;
+use rusqlite::Connection;
+
+use crate::{
+    db::{is_database_file, locate_path, rehash_stored, stored_roots, RehashedFile},
+    hashes::hash::CheckSummary,
+    models::{file_table::FileTable, root_table::RootTable},
+    output::{hash_map, Output, OutputOptions, Record},
+    utils::collect_files,
+};
 
 use super::utils::Mode;
 
 pub struct UseDatabaseMode {
-    pub args: Args,
+    pub db_path: PathBuf,
+    pub files: Vec<PathBuf>,
+    pub output: OutputOptions,
 }
 
 impl Mode for UseDatabaseMode {
     fn run(&self) -> ExitCode {
-        0.into()
+        let mut output = Output::new(&self.output);
+        let db_path = self.db_path.as_path();
+        if !db_path.exists() {
+            eprintln!("{} does not exist", db_path.display());
+            return 255.into();
+        }
+
+        let connection = match Connection::open(db_path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        let roots = stored_roots(&connection);
+        let file_list: Vec<PathBuf> = match collect_files(&self.files) {
+            // DB itself changes whenever it is updated
+            Ok(l) => l
+                .into_iter()
+                .filter(|f| !is_database_file(f, db_path))
+                .collect(),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 255.into();
+            }
+        };
+
+        // the stored record of each file, or None if it is not in DB
+        let located: Vec<Option<FileTable>> = file_list
+            .iter()
+            .map(|f| Self::stored_file(&connection, &roots, f))
+            .collect();
+        let stored_files = located
+            .iter()
+            .zip(file_list.iter())
+            .filter_map(|(s, f)| Some((s.clone()?, f.clone())))
+            .collect();
+        let mut rehashed = rehash_stored(&connection, stored_files).into_iter();
+
+        let mut summary = CheckSummary::default();
+        let mut errors = 0;
+        for (file, located) in file_list.iter().zip(located) {
+            let RehashedFile { stored, actual, .. } = match located {
+                Some(_) => rehashed.next().unwrap(),
+                None => {
+                    summary.failed += 1;
+                    let record = Record {
+                        status: Some("NOT IN DB".to_string()),
+                        ..Record::for_file(file)
+                    };
+                    output.emit(record, |_| println!("{}: NOT IN DB", file.display()));
+                    continue;
+                }
+            };
+
+            let actual = match actual {
+                Ok(a) => a,
+                Err(e) => {
+                    errors += 1;
+                    output.emit(Record::error(file.to_string_lossy(), &e), |_| {
+                        eprintln!("{}: {}", file.display(), e)
+                    });
+                    continue;
+                }
+            };
+
+            for ((algorithm, hash), (_, actual)) in stored.iter().zip(actual) {
+                let result = if *hash == actual {
+                    summary.ok += 1;
+                    "OK"
+                } else {
+                    summary.failed += 1;
+                    "FAILED"
+                };
+
+                let record = Record {
+                    hashes: hash_map([(*algorithm, Some(hash))]),
+                    status: Some(result.to_string()),
+                    ..Record::for_file(file)
+                };
+                output.emit(record, |_| {
+                    println!("{}: {} ({})", file.display(), result, algorithm.name())
+                });
+            }
+        }
+
+        if summary.failed > 0 || errors > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl UseDatabaseMode {
+    fn stored_file(connection: &Connection, roots: &[RootTable], file: &Path) -> Option<FileTable> {
+        let (root_id, full_path) = locate_path(roots, file)?;
+        FileTable::find_by_path(connection, root_id, &full_path)
     }
 }
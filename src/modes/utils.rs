@@ -1,83 +1,369 @@
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::str::FromStr;
 
+use crate::db::HASH_TABLE_FILENAME;
+use crate::hashes::algorithm::Algorithm;
+use crate::hashes::hash::{ChecksumFileUtils, STDIN_FILENAME};
+use crate::openpgp::Keyring;
+use crate::output::{OutputOptions, Template};
+use crate::signature::PublicKey;
+use crate::utils::expand_glob;
 use crate::Args;
 
+use super::audit_db::AuditDatabaseMode;
+use super::audit_hashdeep::AuditHashdeepMode;
 use super::calculate_file_hash::CalculateFileHashMode;
 use super::calculate_stdin_hash::CalculateStdinHashMode;
+use super::convert::ConvertManifestMode;
 use super::create_db::CreateDatabaseMode;
+use super::create_hashdeep::CreateHashdeepMode;
+use super::create_sfv::CreateSfvMode;
+use super::create_sidecar::CreateSidecarMode;
+use super::dedupe_db::{DedupeDatabaseMode, UNDO_LOG_FILENAME};
+use super::diff_manifest::DiffManifestMode;
+use super::duplicates_db::DuplicatesDatabaseMode;
+use super::expect_digest::ExpectDigestMode;
+use super::export_db::ExportDatabaseMode;
+use super::find_db::FindDatabaseMode;
+use super::rebind_root::RebindRootMode;
+use super::scrub_db::ScrubDatabaseMode;
+use super::sign_manifest::SignManifestMode;
+use super::undo_dedupe::UndoDedupeMode;
 use super::unexpected_arguments::UnexpectedArgumentsMode;
 use super::update_db::UpdateDatabaseMode;
 use super::use_db::UseDatabaseMode;
 use super::validate_checksum::ValidateChecksumMode;
-
-#[macro_export]
-macro_rules! print_some {
-    ($hash: tt) => {
-        match $hash {
-            Some(h) => print!("{}  ", h),
-            None => {}
-        }
-    };
-}
+use super::validate_sidecar::ValidateSidecarMode;
 
 pub trait Mode {
     fn run(&self) -> ExitCode;
 }
 
-fn validate_database_arguments(args: &Args) -> Result<(bool, bool, bool), String> {
-    let initialize = args.initialize_database;
-    let update = args.update_database;
-    let use_db = args.use_db;
+// the algorithms of --md5, --sha1 and --sha256, and the ones used in --format
+pub fn select_algorithms(
+    md5: bool,
+    sha1: bool,
+    sha256: bool,
+    output: &OutputOptions,
+) -> Vec<Algorithm> {
+    let mut selected = vec![];
+    if md5 {
+        selected.push(Algorithm::Md5);
+    }
+    if sha1 {
+        selected.push(Algorithm::Sha1);
+    }
+    if sha256 {
+        selected.push(Algorithm::Sha256);
+    }
+
+    match output.template {
+        Some(ref t) => {
+            selected.extend(t.algorithms());
+            Algorithm::ALL
+                .into_iter()
+                .filter(|a| selected.contains(a))
+                .collect()
+        }
+        None if selected.is_empty() => vec![Algorithm::Md5, Algorithm::Sha1, Algorithm::Sha256],
+        None => selected,
+    }
+}
 
-    if initialize && update {
-        return Err(format!("invalid option: --initialize-db with --update-db"));
+// the hashes and the path in the traditional order, unless --format is given
+pub fn with_default_template(output: &OutputOptions, algorithms: &[Algorithm]) -> OutputOptions {
+    let mut output = output.clone();
+    if output.template.is_none() {
+        output.template = Some(Template::from_algorithms(algorithms));
     }
 
-    if use_db && (initialize || update) {
-        return Err(format!(
-            "invalid option: --use-db with --initialize-db or --update-db"
-        ));
+    output
+}
+
+fn validate_database_arguments(args: &Args) -> Result<(), String> {
+    let database_modes = [
+        ("--init-db", args.initialize_database),
+        ("--update-db", args.update_database),
+        ("--use-db", args.use_db),
+        ("--audit-db", args.audit_database),
+        ("--scrub-db", args.scrub_database),
+        ("--rebind", args.rebind_root.is_some()),
+        ("--find-db", args.find_database),
+        ("--duplicates-db", args.duplicates_database),
+        ("--dedupe-db", args.dedupe_database),
+        ("--undo-dedupe", args.undo_dedupe.is_some()),
+        ("--export-db", args.export_database),
+    ];
+
+    let specified: Vec<&str> = database_modes
+        .iter()
+        .filter(|(_, flag)| *flag)
+        .map(|(name, _)| *name)
+        .collect();
+    if specified.len() > 1 {
+        return Err(format!("invalid option: {}", specified.join(" with ")));
     }
 
-    Ok((initialize, update, use_db))
+    Ok(())
 }
 
 pub fn determine_mode(args: &Args) -> Box<dyn Mode> {
     // process DB
-    let (initialize, update, use_db) = match validate_database_arguments(&args) {
-        Ok(flags) => flags,
-        Err(s) => {
-            eprintln!("{}", s);
-            return Box::new(UnexpectedArgumentsMode { args: args.clone() });
-        }
+    if let Err(s) = validate_database_arguments(args) {
+        eprintln!("{}", s);
+        return Box::new(UnexpectedArgumentsMode);
+    }
+
+    let template = match args.format {
+        Some(ref f) => match Template::parse(f) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                eprintln!("invalid option: --format: {}", e);
+                return Box::new(UnexpectedArgumentsMode);
+            }
+        },
+        None => None,
     };
 
-    if initialize {
-        return Box::new(CreateDatabaseMode {});
-    }
+    let output = OutputOptions {
+        format: args.output,
+        columns: args.columns.clone().unwrap_or_default(),
+        template,
+        zero: args.zero,
+    };
 
-    if update {
-        return Box::new(UpdateDatabaseMode {});
+    let db_path = args
+        .db_filepath
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(HASH_TABLE_FILENAME));
+    // the roots to scan, or the current directory
+    let roots = args.files.clone();
+
+    if args.initialize_database {
+        return Box::new(CreateDatabaseMode {
+            db_path: db_path.clone(),
+            roots: roots.unwrap_or_else(|| vec![".".to_string()]),
+            output: output.clone(),
+        });
     }
 
-    if use_db {
-        return Box::new(UseDatabaseMode { args: args.clone() });
+    if args.update_database {
+        return Box::new(UpdateDatabaseMode {
+            db_path: db_path.clone(),
+            roots: roots.unwrap_or_default(),
+            paranoid: args.paranoid,
+            prune: args.prune,
+            output: output.clone(),
+        });
     }
 
-    // passed checksum file
-    if args.checksum_filepath.is_some() {
-        let checksum_filepath = Path::new(match args.checksum_filepath {
-            Some(ref p) => p,
+    if args.use_db {
+        let files = match args.files {
+            Some(ref f) => f.iter().map(PathBuf::from).collect(),
             None => {
-                return Box::new(UnexpectedArgumentsMode { args: args.clone() });
+                eprintln!("invalid option: --use-db requires FILEs");
+                return Box::new(UnexpectedArgumentsMode);
+            }
+        };
+
+        return Box::new(UseDatabaseMode {
+            db_path: db_path.clone(),
+            files,
+            output: output.clone(),
+        });
+    }
+
+    if args.audit_database {
+        return Box::new(AuditDatabaseMode {
+            db_path: db_path.clone(),
+            output: output.clone(),
+        });
+    }
+
+    if args.scrub_database {
+        return Box::new(ScrubDatabaseMode {
+            db_path: db_path.clone(),
+            sample: args.sample,
+            oldest: args.oldest,
+            output: output.clone(),
+        });
+    }
+
+    if let Some(ref root) = args.rebind_root {
+        return Box::new(RebindRootMode {
+            db_path: db_path.clone(),
+            root: root.clone(),
+        });
+    }
+
+    if args.find_database {
+        return Box::new(FindDatabaseMode {
+            db_path: db_path.clone(),
+            digests: args.files.clone(),
+            output: output.clone(),
+        });
+    }
+
+    if args.duplicates_database {
+        return Box::new(DuplicatesDatabaseMode {
+            db_path: db_path.clone(),
+            same_size: args.same_size,
+            output: output.clone(),
+        });
+    }
+
+    if args.dedupe_database {
+        return Box::new(DedupeDatabaseMode {
+            db_path: db_path.clone(),
+            link: args.link,
+            dry_run: args.dry_run,
+            undo_log: args
+                .undo_log
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(UNDO_LOG_FILENAME)),
+            output: output.clone(),
+        });
+    }
+
+    if let Some(ref undo_log) = args.undo_dedupe {
+        return Box::new(UndoDedupeMode {
+            undo_log: undo_log.clone(),
+            output: output.clone(),
+        });
+    }
+
+    if args.export_database {
+        return Box::new(ExportDatabaseMode {
+            db_path: db_path.clone(),
+            output: output.clone(),
+        });
+    }
+
+    // passed hashdeep known file
+    if let Some(ref known_filepath) = args.audit_known_filepath {
+        let targets = match args.files {
+            Some(ref f) => f.iter().map(PathBuf::from).collect(),
+            None => vec![PathBuf::from(".")],
+        };
+
+        return Box::new(AuditHashdeepMode {
+            known_filepath: PathBuf::from(known_filepath),
+            targets,
+            output: output.clone(),
+        });
+    }
+
+    // check sidecar files
+    if args.check_sidecar {
+        let targets = match args.files {
+            Some(ref f) => f.iter().map(PathBuf::from).collect(),
+            None => vec![PathBuf::from(".")],
+        };
+
+        return Box::new(ValidateSidecarMode {
+            targets,
+            output: output.clone(),
+        });
+    }
+
+    // diff checksum files
+    if let Some(ref diff_filepaths) = args.diff_filepaths {
+        return Box::new(DiffManifestMode {
+            old_filepath: PathBuf::from(&diff_filepaths[0]),
+            new_filepath: PathBuf::from(&diff_filepaths[1]),
+            algorithm: args.algorithm,
+            output: output.clone(),
+        });
+    }
+
+    // convert checksum files
+    if let Some(format) = args.convert_format {
+        let checksum_filepaths = match args.files {
+            Some(ref f) => f.iter().flat_map(|p| expand_glob(p)).collect(),
+            None => vec![PathBuf::from(STDIN_FILENAME)],
+        };
+
+        let rebase = match args.rebase {
+            Some(ref r) => match r.split_once('=') {
+                Some((from, to)) => Some((PathBuf::from(from), PathBuf::from(to))),
+                None => {
+                    eprintln!("invalid option: --rebase must be FROM=TO");
+                    return Box::new(UnexpectedArgumentsMode);
+                }
+            },
+            None => None,
+        };
+
+        return Box::new(ConvertManifestMode {
+            checksum_filepaths,
+            format,
+            algorithm: args.algorithm,
+            rebase,
+        });
+    }
+
+    // passed expected digest
+    if let Some(ref digest) = args.expected_digest {
+        let (algorithm, hash) = match ChecksumFileUtils::parse_digest(digest, args.algorithm) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("invalid option: --expect: {}", e);
+                return Box::new(UnexpectedArgumentsMode);
             }
+        };
+
+        let files = match args.files {
+            Some(ref f) => f.iter().map(PathBuf::from).collect(),
+            None => vec![PathBuf::from(STDIN_FILENAME)],
+        };
+
+        return Box::new(ExpectDigestMode {
+            algorithm,
+            hash,
+            files,
+            output: output.clone(),
         });
+    }
+
+    // passed checksum files
+    if let Some(ref checksum_filepaths) = args.checksum_filepaths {
+        let public_key = match args.public_key {
+            Some(ref k) => match PublicKey::read(k) {
+                Ok(k) => Some(k),
+                Err(e) => {
+                    eprintln!("invalid option: --pubkey: {}", e);
+                    return Box::new(UnexpectedArgumentsMode);
+                }
+            },
+            None => None,
+        };
+
+        let keyring = match args.keyring_filepath {
+            Some(ref k) => match Keyring::read(Path::new(k)) {
+                Ok(k) => Some(k),
+                Err(e) => {
+                    eprintln!("invalid option: --keyring: {}: {}", k, e);
+                    return Box::new(UnexpectedArgumentsMode);
+                }
+            },
+            None => None,
+        };
+
+        let checksum_filepaths = checksum_filepaths
+            .iter()
+            .flat_map(|p| expand_glob(p))
+            .collect();
 
         return Box::new(ValidateChecksumMode {
-            checksum_filepath: PathBuf::from(checksum_filepath),
+            checksum_filepaths,
             ignore_missing: args.ignore_missing,
+            unlisted_dir: args.unlisted_dir.as_ref().map(PathBuf::from),
+            strict: args.strict,
+            jobs: args.jobs,
+            algorithm: args.algorithm,
+            public_key,
+            keyring,
+            output: output.clone(),
         });
     }
 
@@ -88,14 +374,37 @@ pub fn determine_mode(args: &Args) -> Box<dyn Mode> {
             .files
             .unwrap()
             .iter()
-            .map(|f| PathBuf::from(f))
+            .map(PathBuf::from)
             .collect();
 
+        if let Some(ref secret_key_filepath) = args.secret_key_filepath {
+            return Box::new(SignManifestMode {
+                secret_key_filepath: PathBuf::from(secret_key_filepath),
+                files,
+            });
+        }
+
+        if args.sfv {
+            return Box::new(CreateSfvMode { files });
+        }
+
+        if let Some(ref algorithms) = args.sidecar_algorithms {
+            return Box::new(CreateSidecarMode {
+                algorithms: algorithms.clone(),
+                files,
+            });
+        }
+
+        if args.hashdeep {
+            return Box::new(CreateHashdeepMode { files });
+        }
+
         return Box::new(CalculateFileHashMode {
             md5: args.md5,
             sha1: args.sha1,
             sha256: args.sha256,
             files,
+            output: output.clone(),
         });
     }
 
@@ -104,5 +413,6 @@ pub fn determine_mode(args: &Args) -> Box<dyn Mode> {
         md5: args.md5,
         sha1: args.sha1,
         sha256: args.sha256,
+        output: output.clone(),
     })
 }
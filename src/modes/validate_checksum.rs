@@ -1,23 +1,132 @@
-use std::{path::PathBuf, process::ExitCode};
+use std::{
+    collections::HashSet,
+    path::{self, Path, PathBuf},
+    process::ExitCode,
+};
 
+use crate::hashes::{algorithm::Algorithm, hash::CheckSummary, manifest::Manifest};
+use crate::openpgp::Keyring;
+use crate::output::{Output, OutputOptions, Record};
+use crate::signature::PublicKey;
+use crate::utils::{collect_files, normalize_path};
 use crate::ChecksumFileUtils;
 
 use super::utils::Mode;
 
 pub struct ValidateChecksumMode {
-    pub checksum_filepath: PathBuf,
+    pub checksum_filepaths: Vec<PathBuf>,
     pub ignore_missing: bool,
+    pub unlisted_dir: Option<PathBuf>,
+    pub strict: bool,
+    pub jobs: Option<usize>,
+    pub algorithm: Option<Algorithm>,
+    pub public_key: Option<PublicKey>,
+    pub keyring: Option<Keyring>,
+    pub output: OutputOptions,
 }
 
 impl Mode for ValidateChecksumMode {
     fn run(&self) -> ExitCode {
-        let file_path = self.checksum_filepath.as_path();
-        match ChecksumFileUtils::check(file_path, self.ignore_missing) {
-            Ok(_) => ExitCode::SUCCESS,
-            Err(e) => {
-                println!("{}", e);
-                ExitCode::FAILURE
+        let mut output = Output::new(&self.output);
+        let mut summary = CheckSummary::default();
+        let mut errors = 0;
+        let mut listed: HashSet<String> = HashSet::new();
+        for file_path in self.checksum_filepaths.iter() {
+            let result = self.read_manifest(file_path).and_then(|m| {
+                listed.extend(m.entries.iter().map(|e| Self::key(Path::new(&e.path))));
+                ChecksumFileUtils::check(&m, self.ignore_missing, self.jobs, &mut output)
+            });
+            match result {
+                Ok(s) => summary.merge(&s),
+                Err(e) => {
+                    output.emit(Record::error(file_path.to_string_lossy(), &e), |_| {
+                        println!("{}: {}", file_path.display(), e)
+                    });
+                    errors += 1;
+                }
             }
         }
+
+        let mut unlisted = 0;
+        if let Some(ref dir) = self.unlisted_dir {
+            // the checksum files themselves and their signatures are not reported
+            listed.extend(self.checksum_filepaths.iter().map(|p| Self::key(p)));
+            if self.public_key.is_some() {
+                listed.extend(
+                    self.checksum_filepaths
+                        .iter()
+                        .filter_map(|p| ChecksumFileUtils::signature_path(p))
+                        .map(|p| Self::key(&p)),
+                );
+            }
+            let file_list = match collect_files(std::slice::from_ref(dir)) {
+                Ok(l) => l,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 255.into();
+                }
+            };
+
+            for file in file_list.iter() {
+                if listed.contains(&Self::key(file)) {
+                    continue;
+                }
+
+                unlisted += 1;
+                let mut record = Record::for_file(file);
+                record.status = Some("UNLISTED".to_string());
+                output.emit(record, |_| println!("{}: UNLISTED", file.display()));
+            }
+        }
+
+        if self.checksum_filepaths.len() > 1 && output.is_text() {
+            println!(
+                "{} checksum files: {} OK, {} FAILED, {} errors",
+                self.checksum_filepaths.len(),
+                summary.ok,
+                summary.failed,
+                errors
+            );
+        }
+
+        if summary.failed > 0 || errors > 0 || (self.strict && unlisted > 0) {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl ValidateChecksumMode {
+    fn read_manifest(&self, file_path: &Path) -> Result<Manifest, String> {
+        if let Some(ref k) = self.public_key {
+            return ChecksumFileUtils::read_signed_manifest(file_path, self.algorithm, k);
+        }
+
+        match self.keyring {
+            Some(ref k) => {
+                let (manifest, signer) =
+                    ChecksumFileUtils::read_clear_signed_manifest(file_path, self.algorithm, k)?;
+                eprintln!(
+                    "{}: good signature from {}{}",
+                    file_path.display(),
+                    signer.fingerprint,
+                    signer
+                        .user_id
+                        .map(|u| format!(" \"{}\"", u))
+                        .unwrap_or_default()
+                );
+                Ok(manifest)
+            }
+            None => ChecksumFileUtils::read_manifest(file_path, self.algorithm),
+        }
+    }
+
+    // "a/b", "./a/b" and "/cwd/a/b" point to the same file
+    fn key(path: &Path) -> String {
+        match path::absolute(path) {
+            Ok(p) => normalize_path(&p),
+            Err(_) => normalize_path(path),
+        }
     }
 }
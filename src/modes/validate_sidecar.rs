@@ -0,0 +1,93 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use crate::{
+    hashes::{algorithm::Algorithm, hash::CheckSummary},
+    output::{Output, OutputOptions, Record},
+    utils::collect_files,
+    ChecksumFileUtils,
+};
+
+use super::utils::Mode;
+
+pub struct ValidateSidecarMode {
+    pub targets: Vec<PathBuf>,
+    pub output: OutputOptions,
+}
+
+impl Mode for ValidateSidecarMode {
+    fn run(&self) -> ExitCode {
+        let mut output = Output::new(&self.output);
+        let sidecars = match self.find_sidecars() {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if sidecars.is_empty() {
+            eprintln!("no sidecar file found");
+            return ExitCode::FAILURE;
+        }
+
+        let mut summary = CheckSummary::default();
+        let mut errors = 0;
+        for sidecar in sidecars.iter() {
+            match ChecksumFileUtils::check_sidecar(sidecar, &mut output) {
+                Ok(s) => summary.merge(&s),
+                Err(e) => {
+                    output.emit(Record::error(sidecar.to_string_lossy(), &e), |_| {
+                        println!("{}: {}", sidecar.display(), e)
+                    });
+                    errors += 1;
+                }
+            }
+        }
+
+        if sidecars.len() > 1 && output.is_text() {
+            println!(
+                "{} sidecar files: {} OK, {} FAILED, {} errors",
+                sidecars.len(),
+                summary.ok,
+                summary.failed,
+                errors
+            );
+        }
+
+        if summary.failed > 0 || errors > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+impl ValidateSidecarMode {
+    fn find_sidecars(&self) -> Result<Vec<PathBuf>, String> {
+        let mut sidecars = vec![];
+        for target in self.targets.iter() {
+            if target.is_dir() {
+                let file_list = collect_files(std::slice::from_ref(target))?;
+                sidecars.extend(
+                    file_list
+                        .into_iter()
+                        .filter(|f| ChecksumFileUtils::sidecar_target(f).is_some()),
+                );
+            } else if ChecksumFileUtils::sidecar_target(target).is_some() {
+                sidecars.push(target.clone());
+            } else if target.exists() {
+                // FILE -> FILE.md5, FILE.sha1, ...
+                sidecars.extend(
+                    Algorithm::ALL
+                        .iter()
+                        .map(|a| ChecksumFileUtils::sidecar_path(target, *a))
+                        .filter(|s| s.exists()),
+                );
+            } else {
+                return Err(format!("{} does not exist", target.to_string_lossy()));
+            }
+        }
+
+        Ok(sidecars)
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use pgp::{
+    composed::{CleartextSignedMessage, Deserializable, SignedPublicKey, SignedPublicSubKey},
+    packet::{KeyFlags, Signature, SignatureType},
+    types::{KeyDetails, Tag, Timestamp},
+};
+
+// clear-signed checksum files are verified by rPGP, with the keys and subkeys that are
+// bound by valid self-signatures, not revoked, not expired and allowed to sign
+const BEGIN_SIGNED_MESSAGE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+
+pub struct ClearSigned {
+    pub text: String,
+    message: CleartextSignedMessage,
+}
+
+// a key that may sign, the primary key of the certificate or one of its subkeys
+struct SigningKey {
+    certificate: SignedPublicKey,
+    subkey: Option<usize>,
+}
+
+pub struct Keyring {
+    keys: Vec<SigningKey>,
+}
+
+// the signing key of a good signature
+pub struct Signer {
+    pub fingerprint: String,
+    pub user_id: Option<String>,
+}
+
+impl ClearSigned {
+    pub fn is_clear_signed(content: &str) -> bool {
+        content.trim_start().starts_with(BEGIN_SIGNED_MESSAGE)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let (message, _) = CleartextSignedMessage::from_string(content.trim_start())
+            .map_err(|e| format!("invalid OpenPGP clear-signed file: {}", e))?;
+
+        // the signed text has CRLF line breaks
+        let mut text = message.signed_text().replace("\r\n", "\n");
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(Self { text, message })
+    }
+
+    pub fn verify(&self, keyring: &Keyring) -> Result<Signer, String> {
+        let text = self.message.signed_text();
+        let mut error = "OpenPGP signature not found".to_string();
+        for signature in self.message.signatures() {
+            // the signatures over the text, not over keys or user ids
+            if !matches!(
+                signature.typ(),
+                Some(SignatureType::Binary | SignatureType::Text)
+            ) {
+                error = "unsupported OpenPGP signature type".to_string();
+                continue;
+            }
+
+            if is_expired(signature.created(), signature.signature_expiration_time()) {
+                error = "OpenPGP signature is expired".to_string();
+                continue;
+            }
+
+            let key = match keyring.keys.iter().find(|k| k.is_issuer(signature)) {
+                Some(k) => k,
+                None => {
+                    error = format!("public key not found in keyring: {}", issuer(signature));
+                    continue;
+                }
+            };
+
+            match key.verify(signature, text.as_bytes()) {
+                Ok(_) => {
+                    return Ok(Signer {
+                        fingerprint: key.fingerprint(),
+                        user_id: key.user_id(),
+                    });
+                }
+                Err(_) => error = "bad OpenPGP signature".to_string(),
+            }
+        }
+
+        Err(error)
+    }
+}
+
+impl SigningKey {
+    fn is_issuer(&self, signature: &Signature) -> bool {
+        let (key_id, fingerprint) = match self.subkey {
+            Some(i) => {
+                let subkey = &self.certificate.public_subkeys[i].key;
+                (subkey.legacy_key_id(), subkey.fingerprint())
+            }
+            None => (
+                self.certificate.primary_key.legacy_key_id(),
+                self.certificate.primary_key.fingerprint(),
+            ),
+        };
+
+        signature.issuer_fingerprint().contains(&&fingerprint)
+            || signature.issuer_key_id().contains(&&key_id)
+    }
+
+    fn verify(&self, signature: &Signature, text: &[u8]) -> pgp::errors::Result<()> {
+        match self.subkey {
+            Some(i) => signature.verify(&self.certificate.public_subkeys[i].key, text),
+            None => signature.verify(&self.certificate.primary_key, text),
+        }
+    }
+
+    fn fingerprint(&self) -> String {
+        match self.subkey {
+            Some(i) => format!("{:X}", self.certificate.public_subkeys[i].key.fingerprint()),
+            None => format!("{:X}", self.certificate.primary_key.fingerprint()),
+        }
+    }
+
+    // the primary user id, or the first one
+    fn user_id(&self) -> Option<String> {
+        let users = &self.certificate.details.users;
+        users
+            .iter()
+            .find(|u| u.is_primary())
+            .or(users.first())
+            .map(|u| String::from_utf8_lossy(u.id.id()).to_string())
+    }
+}
+
+impl Keyring {
+    // binary or armored public keys
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("{}", e))?;
+        let (certificates, _) = SignedPublicKey::from_reader_many(bytes.as_slice())
+            .map_err(|e| format!("invalid OpenPGP keyring: {}", e))?;
+
+        let mut keys = vec![];
+        for certificate in certificates {
+            // the keys that cannot be parsed are skipped
+            let Ok(certificate) = certificate else {
+                continue;
+            };
+            let Some(self_signature) = Self::valid_primary(&certificate) else {
+                continue;
+            };
+
+            let subkeys: Vec<usize> = (0..certificate.public_subkeys.len())
+                .filter(|i| Self::valid_subkey(&certificate, &certificate.public_subkeys[*i]))
+                .collect();
+            // without key flags, the primary key is assumed to sign
+            let flags = self_signature.key_flags();
+            let primary = flags == KeyFlags::default() || flags.sign();
+            if primary {
+                keys.push(SigningKey {
+                    certificate: certificate.clone(),
+                    subkey: None,
+                });
+            }
+            for i in subkeys {
+                keys.push(SigningKey {
+                    certificate: certificate.clone(),
+                    subkey: Some(i),
+                });
+            }
+        }
+
+        if keys.is_empty() {
+            return Err("no valid signing key in keyring".to_string());
+        }
+
+        Ok(Self { keys })
+    }
+
+    // the latest valid self-signature of the primary key, if it is neither revoked nor expired
+    fn valid_primary(certificate: &SignedPublicKey) -> Option<Signature> {
+        let primary = &certificate.primary_key;
+        let revoked = certificate
+            .details
+            .revocation_signatures
+            .iter()
+            .any(|s| s.verify_key(primary).is_ok());
+        if revoked {
+            return None;
+        }
+
+        // the certifications by other keys are ignored
+        let certifications = certificate.details.users.iter().flat_map(|u| {
+            u.signatures
+                .iter()
+                .filter(|s| s.verify_certification(primary, Tag::UserId, &u.id).is_ok())
+        });
+        let direct = certificate
+            .details
+            .direct_signatures
+            .iter()
+            .filter(|s| s.verify_key(primary).is_ok());
+        let latest = certifications
+            .chain(direct)
+            .max_by_key(|s| s.created().map(|t| t.as_secs()))?;
+
+        if is_expired(Some(primary.created_at()), latest.key_expiration_time()) {
+            return None;
+        }
+        Some(latest.clone())
+    }
+
+    // a signing subkey bound by the primary key, with the back signature of the subkey
+    fn valid_subkey(certificate: &SignedPublicKey, subkey: &SignedPublicSubKey) -> bool {
+        let primary = &certificate.primary_key;
+        let verified: Vec<&Signature> = subkey
+            .signatures
+            .iter()
+            .filter(|s| s.verify_subkey_binding(primary, &subkey.key).is_ok())
+            .collect();
+        if verified
+            .iter()
+            .any(|s| s.typ() == Some(SignatureType::SubkeyRevocation))
+        {
+            return false;
+        }
+
+        let Some(binding) = verified
+            .into_iter()
+            .filter(|s| s.typ() == Some(SignatureType::SubkeyBinding))
+            .max_by_key(|s| s.created().map(|t| t.as_secs()))
+        else {
+            return false;
+        };
+
+        binding.key_flags().sign()
+            && binding
+                .embedded_signature()
+                .is_some_and(|b| b.verify_primary_key_binding(&subkey.key, primary).is_ok())
+            && !is_expired(Some(subkey.key.created_at()), binding.key_expiration_time())
+    }
+}
+
+// the fingerprint or the key id of the issuer
+fn issuer(signature: &Signature) -> String {
+    match signature.issuer_fingerprint().first() {
+        Some(f) => format!("{:X}", f),
+        None => signature
+            .issuer_key_id()
+            .first()
+            .map(|k| k.as_ref().iter().map(|b| format!("{:02X}", b)).collect())
+            .unwrap_or_default(),
+    }
+}
+
+// the expiration time is relative to the creation time, and zero never expires
+fn is_expired(created: Option<Timestamp>, expiration: Option<pgp::types::Duration>) -> bool {
+    match (created, expiration) {
+        (Some(c), Some(e)) if e.as_secs() > 0 => {
+            SystemTime::from(c) + Duration::from_secs(e.as_secs() as u64) <= SystemTime::now()
+        }
+        _ => false,
+    }
+}
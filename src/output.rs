@@ -0,0 +1,378 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, Stdout, Write},
+    path::{self, Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::hashes::algorithm::Algorithm;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    Path,
+    Relpath,
+    Abspath,
+    Size,
+    Mtime,
+    Crc32,
+    Md5,
+    Sha1,
+    Sha256,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    Blake3,
+    Status,
+    Group,
+    Error,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 6] = [
+        Column::Path,
+        Column::Size,
+        Column::Mtime,
+        Column::Md5,
+        Column::Sha1,
+        Column::Sha256,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Relpath => "relpath",
+            Column::Abspath => "abspath",
+            Column::Size => "size",
+            Column::Mtime => "mtime",
+            Column::Crc32 => Algorithm::Crc32.name(),
+            Column::Md5 => Algorithm::Md5.name(),
+            Column::Sha1 => Algorithm::Sha1.name(),
+            Column::Sha256 => Algorithm::Sha256.name(),
+            Column::Sha3_256 => Algorithm::Sha3_256.name(),
+            Column::Blake3 => Algorithm::Blake3.name(),
+            Column::Status => "status",
+            Column::Group => "group",
+            Column::Error => "error",
+        }
+    }
+
+    fn value(&self, record: &Record) -> String {
+        match self {
+            Column::Path => record.path.clone(),
+            Column::Relpath => {
+                let path = PathBuf::from(&record.path);
+                match env::current_dir() {
+                    Ok(cwd) if path.starts_with(&cwd) => path
+                        .strip_prefix(&cwd)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                    _ => record.path.clone(),
+                }
+            }
+            Column::Abspath => match path::absolute(&record.path) {
+                Ok(p) => p.to_string_lossy().to_string(),
+                Err(_) => record.path.clone(),
+            },
+            Column::Size => record.size.map(|s| s.to_string()).unwrap_or_default(),
+            Column::Mtime => record.mtime.map(|m| m.to_string()).unwrap_or_default(),
+            Column::Crc32
+            | Column::Md5
+            | Column::Sha1
+            | Column::Sha256
+            | Column::Sha3_256
+            | Column::Blake3 => record.hashes.get(self.name()).cloned().unwrap_or_default(),
+            Column::Status => record.status.clone().unwrap_or_default(),
+            Column::Group => record.group.map(|g| g.to_string()).unwrap_or_default(),
+            Column::Error => record.error.clone().unwrap_or_default(),
+        }
+    }
+
+    fn algorithm(&self) -> Option<Algorithm> {
+        match self {
+            Column::Crc32 => Some(Algorithm::Crc32),
+            Column::Md5 => Some(Algorithm::Md5),
+            Column::Sha1 => Some(Algorithm::Sha1),
+            Column::Sha256 => Some(Algorithm::Sha256),
+            Column::Sha3_256 => Some(Algorithm::Sha3_256),
+            Column::Blake3 => Some(Algorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    fn from_algorithm(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Crc32 => Column::Crc32,
+            Algorithm::Md5 => Column::Md5,
+            Algorithm::Sha1 => Column::Sha1,
+            Algorithm::Sha256 => Column::Sha256,
+            Algorithm::Sha3_256 => Column::Sha3_256,
+            Algorithm::Blake3 => Column::Blake3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Column),
+}
+
+// e.g., "{sha256}  {size}  {path}"
+// the placeholders are the names of the columns, and "{{" and "}}" are literal braces
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(format!("unclosed placeholder: {{{}", name));
+                            }
+                        }
+                    }
+
+                    let column = match Column::from_str(&name, true) {
+                        Ok(c) => c,
+                        Err(_) => {
+                            return Err(format!("unknown placeholder: {{{}}}", name));
+                        }
+                    };
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(column));
+                }
+                '}' => {
+                    return Err("unmatched '}' in format".to_string());
+                }
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    // hashes  ...  path
+    pub fn from_algorithms(algorithms: &[Algorithm]) -> Self {
+        let mut segments = vec![];
+        for algorithm in algorithms.iter() {
+            segments.push(Segment::Placeholder(Column::from_algorithm(*algorithm)));
+            segments.push(Segment::Literal("  ".to_string()));
+        }
+        segments.push(Segment::Placeholder(Column::Path));
+
+        Self { segments }
+    }
+
+    pub fn algorithms(&self) -> Vec<Algorithm> {
+        self.segments
+            .iter()
+            .filter_map(|s| match s {
+                Segment::Placeholder(c) => c.algorithm(),
+                Segment::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    pub fn render(&self, record: &Record) -> String {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Literal(l) => l.clone(),
+                Segment::Placeholder(c) => c.value(record),
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub columns: Vec<Column>,
+    pub template: Option<Template>,
+    pub zero: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Record {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    // seconds since the UNIX epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub hashes: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_path: Option<String>,
+    // the files with the same hash have the same group number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Record {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn for_file(path: &Path) -> Self {
+        let metadata = path.metadata().ok();
+        Self {
+            size: metadata.as_ref().map(|m| m.len()),
+            mtime: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            ..Self::new(path.to_string_lossy())
+        }
+    }
+
+    pub fn error(path: impl Into<String>, error: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            status: Some("ERROR".to_string()),
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn hash_map<const N: usize>(
+    hashes: [(Algorithm, Option<&String>); N],
+) -> BTreeMap<String, String> {
+    hashes
+        .into_iter()
+        .filter_map(|(a, h)| h.map(|h| (a.name().to_string(), h.to_owned())))
+        .collect()
+}
+
+// the hashes read from DB, which the records own
+pub fn owned_hash_map(hashes: &[(Algorithm, String)]) -> BTreeMap<String, String> {
+    hashes
+        .iter()
+        .map(|(a, h)| (a.name().to_string(), h.clone()))
+        .collect()
+}
+
+pub struct Output {
+    options: OutputOptions,
+    records: Vec<Record>,
+    writer: Option<csv::Writer<Stdout>>,
+}
+
+impl Output {
+    pub fn new(options: &OutputOptions) -> Self {
+        let delimiter = match options.format {
+            OutputFormat::Csv => Some(b','),
+            OutputFormat::Tsv => Some(b'\t'),
+            _ => None,
+        };
+        let writer = delimiter.map(|d| {
+            csv::WriterBuilder::new()
+                .delimiter(d)
+                .from_writer(io::stdout())
+        });
+
+        let mut output = Self {
+            options: options.clone(),
+            records: vec![],
+            writer,
+        };
+        if output.options.columns.is_empty() {
+            output.options.columns = Column::DEFAULT.to_vec();
+        }
+
+        output.write_row(|c| c.name().to_string());
+        output
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.options.format == OutputFormat::Text
+    }
+
+    // `text` prints the record in the traditional format, unless a template is given
+    pub fn emit<F>(&mut self, record: Record, text: F)
+    where
+        F: FnOnce(&Record),
+    {
+        match self.options.format {
+            OutputFormat::Text => match self.options.template {
+                Some(ref t) => {
+                    let terminator = if self.options.zero { '\0' } else { '\n' };
+                    let mut stdout = io::stdout().lock();
+                    write!(stdout, "{}{}", t.render(&record), terminator).unwrap();
+                }
+                None => text(&record),
+            },
+            OutputFormat::Ndjson => println!("{}", serde_json::to_string(&record).unwrap()),
+            OutputFormat::Json => self.records.push(record),
+            OutputFormat::Csv | OutputFormat::Tsv => self.write_row(|c| c.value(&record)),
+        }
+    }
+
+    fn write_row<F>(&mut self, value: F)
+    where
+        F: Fn(&Column) -> String,
+    {
+        if let Some(ref mut writer) = self.writer {
+            let row: Vec<String> = self.options.columns.iter().map(value).collect();
+            writer.write_record(&row).unwrap();
+            writer.flush().unwrap();
+        }
+    }
+}
+
+impl Drop for Output {
+    // the JSON array is printed at once, even if the mode returns early
+    fn drop(&mut self) {
+        if self.options.format == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(&self.records).unwrap());
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::prelude::*;
+use crypto::{
+    bcrypt_pbkdf::bcrypt_pbkdf,
+    blake2b::Blake2b,
+    digest::Digest,
+    scrypt::{scrypt, ScryptParams},
+    sha2::Sha512,
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+// minisign and signify share the Ed25519 key and signature layout:
+// algorithm (2 bytes) + key id (8 bytes) + public key (32 bytes) or signature (64 bytes)
+const ALGORITHM_ED25519: &[u8; 2] = b"Ed";
+// minisign signs the BLAKE2b-512 hash of the file
+const ALGORITHM_ED25519_PREHASHED: &[u8; 2] = b"ED";
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const KDF_SCRYPT: &[u8; 2] = b"Sc";
+const KDF_BCRYPT: &[u8; 2] = b"BK";
+
+const UNTRUSTED_COMMENT: &str = "untrusted comment: ";
+const TRUSTED_COMMENT: &str = "trusted comment: ";
+
+const PUBLIC_KEY_LENGTH: usize = 42;
+const SIGNATURE_LENGTH: usize = 74;
+const SIGNIFY_SECRET_KEY_LENGTH: usize = 104;
+const MINISIGN_SECRET_KEY_LENGTH: usize = 158;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureFormat {
+    Minisign,
+    Signify,
+}
+
+impl SignatureFormat {
+    pub const ALL: [SignatureFormat; 2] = [SignatureFormat::Minisign, SignatureFormat::Signify];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            SignatureFormat::Minisign => "minisig",
+            SignatureFormat::Signify => "sig",
+        }
+    }
+
+    // SHA256SUMS -> SHA256SUMS.minisig
+    pub fn signature_path(&self, path: &Path) -> PathBuf {
+        let mut signature = path.as_os_str().to_owned();
+        signature.push(".");
+        signature.push(self.extension());
+        PathBuf::from(signature)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PublicKey {
+    id: [u8; 8],
+    key: VerifyingKey,
+}
+
+pub struct SecretKey {
+    format: SignatureFormat,
+    id: [u8; 8],
+    key: SigningKey,
+}
+
+// the base64 line following the untrusted comment
+fn decode_key(content: &str) -> Result<Vec<u8>, String> {
+    let line = content
+        .lines()
+        .map(|l| l.trim())
+        .find(|l| !l.is_empty() && !l.starts_with(UNTRUSTED_COMMENT))
+        .ok_or("key not found")?;
+
+    BASE64_STANDARD
+        .decode(line)
+        .map_err(|e| format!("invalid key: {}", e))
+}
+
+fn blake2b_512(message: &[u8]) -> [u8; 64] {
+    let mut blake2b = Blake2b::new(64);
+    blake2b.input(message);
+    let mut hash = [0u8; 64];
+    blake2b.result(&mut hash);
+    hash
+}
+
+// the scrypt parameters of libsodium, which minisign uses
+fn scrypt_params(opslimit: u64, memlimit: u64) -> ScryptParams {
+    let opslimit = opslimit.max(32768);
+    let r = 8;
+    let log_n_for = |max_n: u64| (1..63).find(|n| (1u64 << n) > max_n / 2).unwrap_or(63);
+    if opslimit < memlimit / 32 {
+        let log_n = log_n_for(opslimit / (r * 4));
+        ScryptParams::new(log_n, r as u32, 1)
+    } else {
+        let log_n = log_n_for(memlimit / (r * 128));
+        let max_rp = ((opslimit / 4) / (1u64 << log_n)).min(0x3fffffff);
+        ScryptParams::new(log_n, r as u32, (max_rp / r).max(1) as u32)
+    }
+}
+
+impl PublicKey {
+    // a public key file, or the base64 key itself
+    pub fn read(key: &str) -> Result<Self, String> {
+        let content = match fs::read_to_string(key) {
+            Ok(c) => c,
+            Err(_) if !Path::new(key).exists() => key.to_owned(),
+            Err(e) => return Err(format!("{}: {}", key, e)),
+        };
+
+        let bytes = decode_key(&content)?;
+        if bytes.len() != PUBLIC_KEY_LENGTH || &bytes[0..2] != ALGORITHM_ED25519 {
+            return Err("unsupported public key".to_string());
+        }
+
+        let key = VerifyingKey::from_bytes(bytes[10..42].try_into().unwrap())
+            .map_err(|e| format!("invalid public key: {}", e))?;
+
+        Ok(Self {
+            id: bytes[2..10].try_into().unwrap(),
+            key,
+        })
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &str) -> Result<(), String> {
+        let mut lines = signature
+            .lines()
+            .filter(|l| !l.starts_with(UNTRUSTED_COMMENT));
+        let bytes = match lines.next().map(|l| BASE64_STANDARD.decode(l.trim())) {
+            Some(Ok(b)) if b.len() == SIGNATURE_LENGTH => b,
+            _ => return Err("invalid signature".to_string()),
+        };
+        if bytes[2..10] != self.id {
+            return Err(format!(
+                "signature key id {} does not match the public key",
+                bytes[2..10]
+                    .iter()
+                    .map(|b| format!("{:02X}", b))
+                    .collect::<String>()
+            ));
+        }
+        let signature = Signature::from_bytes(bytes[10..74].try_into().unwrap());
+
+        let verified = match &bytes[0..2] {
+            a if a == ALGORITHM_ED25519 => self.key.verify(message, &signature),
+            a if a == ALGORITHM_ED25519_PREHASHED => {
+                self.key.verify(&blake2b_512(message), &signature)
+            }
+            _ => return Err("unsupported signature algorithm".to_string()),
+        };
+        if verified.is_err() {
+            return Err("signature verification failed".to_string());
+        }
+
+        // minisign also signs the trusted comment with the signature (signify has none)
+        let trusted_comment = match lines.next() {
+            Some(l) => match l.strip_prefix(TRUSTED_COMMENT) {
+                Some(c) => c,
+                None => return Err("invalid trusted comment".to_string()),
+            },
+            None => return Ok(()),
+        };
+        let global_signature = match lines.next().map(|l| BASE64_STANDARD.decode(l.trim())) {
+            Some(Ok(b)) if b.len() == 64 => Signature::from_bytes(b[..].try_into().unwrap()),
+            _ => return Err("invalid global signature".to_string()),
+        };
+
+        let mut signed = signature.to_bytes().to_vec();
+        signed.extend_from_slice(trusted_comment.as_bytes());
+        match self.key.verify(&signed, &global_signature) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("trusted comment verification failed".to_string()),
+        }
+    }
+}
+
+impl SecretKey {
+    // the passphrase is asked only if the key is encrypted
+    pub fn read<F>(path: &Path, passphrase: F) -> Result<Self, String>
+    where
+        F: Fn() -> Result<String, String>,
+    {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}", e))?;
+        let bytes = decode_key(&content)?;
+        if !bytes.starts_with(ALGORITHM_ED25519) {
+            return Err("unsupported secret key".to_string());
+        }
+
+        match bytes.len() {
+            MINISIGN_SECRET_KEY_LENGTH => Self::read_minisign(&bytes, passphrase),
+            SIGNIFY_SECRET_KEY_LENGTH => Self::read_signify(&bytes, passphrase),
+            _ => Err("unsupported secret key".to_string()),
+        }
+    }
+
+    fn read_minisign<F>(bytes: &[u8], passphrase: F) -> Result<Self, String>
+    where
+        F: Fn() -> Result<String, String>,
+    {
+        // algorithm, kdf, checksum algorithm, salt, opslimit, memlimit,
+        // and the key id, the secret key and the checksum encrypted by the kdf
+        let kdf = &bytes[2..4];
+        let salt = &bytes[6..38];
+        let opslimit = u64::from_le_bytes(bytes[38..46].try_into().unwrap());
+        let memlimit = u64::from_le_bytes(bytes[46..54].try_into().unwrap());
+        let mut keynum = bytes[54..158].to_vec();
+        if kdf == KDF_SCRYPT {
+            let mut stream = vec![0u8; keynum.len()];
+            scrypt(
+                passphrase()?.as_bytes(),
+                salt,
+                &scrypt_params(opslimit, memlimit),
+                &mut stream,
+            );
+            keynum.iter_mut().zip(stream).for_each(|(k, s)| *k ^= s);
+        } else if kdf != KDF_NONE {
+            return Err("unsupported key derivation".to_string());
+        }
+
+        let mut blake2b = Blake2b::new(32);
+        blake2b.input(&bytes[0..2]);
+        blake2b.input(&keynum[0..72]);
+        let mut checksum = [0u8; 32];
+        blake2b.result(&mut checksum);
+        if checksum[..] != keynum[72..104] {
+            return Err("wrong passphrase or corrupted secret key".to_string());
+        }
+
+        Ok(Self {
+            format: SignatureFormat::Minisign,
+            id: keynum[0..8].try_into().unwrap(),
+            key: SigningKey::from_bytes(keynum[8..40].try_into().unwrap()),
+        })
+    }
+
+    fn read_signify<F>(bytes: &[u8], passphrase: F) -> Result<Self, String>
+    where
+        F: Fn() -> Result<String, String>,
+    {
+        // algorithm, kdf, rounds, salt, checksum, key id, secret key
+        if &bytes[2..4] != KDF_BCRYPT {
+            return Err("unsupported key derivation".to_string());
+        }
+        let rounds = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+        let salt = &bytes[8..24];
+        let mut secret = bytes[40..104].to_vec();
+        if rounds > 0 {
+            let mut stream = [0u8; 64];
+            bcrypt_pbkdf(passphrase()?.as_bytes(), salt, rounds, &mut stream);
+            secret.iter_mut().zip(stream).for_each(|(k, s)| *k ^= s);
+        }
+
+        let mut sha512 = Sha512::new();
+        sha512.input(&secret);
+        let mut checksum = [0u8; 64];
+        sha512.result(&mut checksum);
+        if checksum[0..8] != bytes[24..32] {
+            return Err("wrong passphrase or corrupted secret key".to_string());
+        }
+
+        Ok(Self {
+            format: SignatureFormat::Signify,
+            id: bytes[32..40].try_into().unwrap(),
+            key: SigningKey::from_bytes(secret[0..32].try_into().unwrap()),
+        })
+    }
+
+    pub fn format(&self) -> SignatureFormat {
+        self.format
+    }
+
+    // the content of the signature file
+    pub fn sign(&self, message: &[u8], file_name: &str) -> String {
+        let encode = |algorithm: &[u8], signature: &Signature| {
+            let mut bytes = algorithm.to_vec();
+            bytes.extend_from_slice(&self.id);
+            bytes.extend_from_slice(&signature.to_bytes());
+            BASE64_STANDARD.encode(bytes)
+        };
+
+        match self.format {
+            SignatureFormat::Signify => {
+                let signature = self.key.sign(message);
+                format!(
+                    "{}verify with rhh public key\n{}\n",
+                    UNTRUSTED_COMMENT,
+                    encode(ALGORITHM_ED25519, &signature)
+                )
+            }
+            SignatureFormat::Minisign => {
+                let signature = self.key.sign(&blake2b_512(message));
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                let trusted_comment =
+                    format!("timestamp:{}\tfile:{}\thashed", timestamp, file_name);
+                let mut signed = signature.to_bytes().to_vec();
+                signed.extend_from_slice(trusted_comment.as_bytes());
+                let global_signature = self.key.sign(&signed);
+
+                format!(
+                    "{}signature from rhh secret key\n{}\n{}{}\n{}\n",
+                    UNTRUSTED_COMMENT,
+                    encode(ALGORITHM_ED25519_PREHASHED, &signature),
+                    TRUSTED_COMMENT,
+                    trusted_comment,
+                    BASE64_STANDARD.encode(global_signature.to_bytes())
+                )
+            }
+        }
+    }
+}
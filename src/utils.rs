@@ -1,10 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    fs::{File, Metadata},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
 
 use glob::glob;
 
 pub fn glob_with_recursive<F>(pattern: &str, handler: &mut F)
 where
-    F: FnMut(&PathBuf) -> (),
+    F: FnMut(&PathBuf),
 {
     glob(pattern)
         .expect("Failed to read glob pattern")
@@ -19,3 +23,210 @@ where
             Err(e) => println!("{:?}", e),
         });
 }
+
+pub fn expand_glob(pattern: &str) -> Vec<PathBuf> {
+    // shells such as Command Prompt do not expand wildcards
+    let path = PathBuf::from(pattern);
+    if path.exists() {
+        return vec![path];
+    }
+
+    let matched: Vec<PathBuf> = match glob(pattern) {
+        Ok(paths) => paths.filter_map(|p| p.ok()).collect(),
+        Err(_) => vec![],
+    };
+
+    if matched.is_empty() {
+        vec![path]
+    } else {
+        matched
+    }
+}
+
+pub fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut file_list: Vec<PathBuf> = vec![];
+    for path in paths.iter() {
+        if !path.exists() {
+            return Err(format!("{} does not exist", path.to_string_lossy()));
+        }
+
+        if path.is_dir() {
+            glob_with_recursive(&format!("{}/*", path.display()), &mut |p| {
+                file_list.push(p.clone());
+            });
+        } else {
+            file_list.push(path.clone());
+        }
+    }
+
+    Ok(file_list)
+}
+
+// the path relative to the current directory if it is under it
+pub fn relative_to_cwd(path: &Path) -> PathBuf {
+    let cwd = match std::env::current_dir().and_then(std::fs::canonicalize) {
+        Ok(c) => c,
+        Err(_) => return path.to_path_buf(),
+    };
+
+    match path.strip_prefix(&cwd) {
+        Ok(p) if p.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(p) => p.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+pub fn normalize_path(path: &Path) -> String {
+    // "./a/b" and "a/b" point to the same file
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .to_string()
+}
+
+// the device the file is on, or 0 if unknown
+#[cfg(unix)]
+pub fn device_of(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::metadata(path).map(|m| m.dev()).unwrap_or(0)
+}
+
+#[cfg(not(unix))]
+pub fn device_of(_path: &Path) -> u64 {
+    0
+}
+
+// ctime in nanoseconds since the UNIX epoch and inode, which are unknown on other platforms
+#[cfg(unix)]
+pub fn ctime_and_inode(metadata: &Metadata) -> (Option<i64>, Option<i64>) {
+    use std::os::unix::fs::MetadataExt;
+
+    let ctime = metadata.ctime() * 1_000_000_000 + metadata.ctime_nsec();
+    (Some(ctime), Some(metadata.ino() as i64))
+}
+
+#[cfg(not(unix))]
+pub fn ctime_and_inode(_metadata: &Metadata) -> (Option<i64>, Option<i64>) {
+    (None, None)
+}
+
+// whether the device is a spinning disk, so its files should be read one by one
+#[cfg(target_os = "linux")]
+pub fn is_rotational(device: u64) -> bool {
+    let major = ((device >> 8) & 0xfff) | ((device >> 32) & !0xfff);
+    let minor = (device & 0xff) | ((device >> 12) & !0xff);
+    let sys_path = PathBuf::from(format!("/sys/dev/block/{}:{}", major, minor));
+
+    // a partition has the queue in its parent
+    [sys_path.join("queue"), sys_path.join("../queue")]
+        .iter()
+        .filter_map(|q| std::fs::read_to_string(q.join("rotational")).ok())
+        .any(|r| r.trim() == "1")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_rotational(_device: u64) -> bool {
+    false
+}
+
+// whether the two files have the same bytes, read in chunks
+pub fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut a, mut b) = (File::open(a)?, File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let (mut buf_a, mut buf_b) = (vec![0u8; 1 << 16], vec![0u8; 1 << 16]);
+    loop {
+        let n = read_chunk(&mut a, &mut buf_a)?;
+        if n != read_chunk(&mut b, &mut buf_b)? || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+fn read_chunk(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match file.read(&mut buf[n..])? {
+            0 => break,
+            m => n += m,
+        }
+    }
+    Ok(n)
+}
+
+// copy the bytes by read and write, since fs::copy may share the extents on btrfs
+pub fn copy_contents(from: &Path, to: &mut File) -> io::Result<()> {
+    let mut from = File::open(from)?;
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        match read_chunk(&mut from, &mut buf)? {
+            0 => return Ok(()),
+            n => to.write_all(&buf[..n])?,
+        }
+    }
+}
+
+// whether the two paths are hardlinks of the same file
+#[cfg(unix)]
+pub fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+pub fn is_same_file(_a: &Metadata, _b: &Metadata) -> bool {
+    false
+}
+
+// share the extents of source with dest by FICLONE, supported on btrfs and XFS
+#[cfg(target_os = "linux")]
+pub fn reflink(source: &File, dest: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: both file descriptors are open during the call
+    match unsafe { libc::ioctl(dest.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reflink(_source: &File, _dest: &File) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflink is not supported on this platform",
+    ))
+}
+
+// the permission bits of the file, which are unknown on other platforms
+#[cfg(unix)]
+pub fn mode_of(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+pub fn mode_of(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+pub fn set_mode(file: &File, mode: u32) -> io::Result<()> {
+    use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+    file.set_permissions(Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_file: &File, _mode: u32) -> io::Result<()> {
+    Ok(())
+}